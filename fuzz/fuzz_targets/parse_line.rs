@@ -0,0 +1,33 @@
+// Fuzzes the events file line parser used by `load_events`.
+// Run with: cargo +nightly fuzz run parse_line
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+#[allow(dead_code)]
+#[path = "../../src/parser.rs"]
+mod parser;
+
+use parser::{Line, parse_line};
+
+fuzz_target!(|data: &[u8]| {
+    let text = String::from_utf8_lossy(data);
+    for line in text.lines() {
+        let (parsed, errors) = parse_line(line);
+
+        // Every line is either usable or explained by at least one diagnostic
+        if parsed == Line::Invalid {
+            assert!(!errors.is_empty(), "invalid line without a diagnostic: {:?}", line);
+        }
+        for error in &errors {
+            assert!(error.column >= 1 && error.column <= line.chars().count() + 1, "column out of range for {:?}: {:?}", line, error);
+        }
+
+        // Expanding a rule must not panic either, including at the edges of the supported date range
+        if let Line::Event(event_line) = parsed {
+            for year in [i32::MIN, -1, 0, 1582, 1583, 2024, 2100, 262_142, 262_143, i32::MAX] {
+                let _ = event_line.rule.date_in_year(year);
+            }
+        }
+    }
+});
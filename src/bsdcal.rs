@@ -0,0 +1,194 @@
+// Import and export of BSD calendar(1) files: "DATE<tab>description" lines with fixed dates,
+// Easter offsets and Nth weekdays of a month. Everything else is listed as skipped on import.
+
+use crate::parser::{EventLine, Rule, parse_rule};
+use crate::{ImportReport, RuleSet, month_from_name, short_month, short_weekday, weekday_from_name};
+use chrono::{Datelike, Weekday};
+
+pub fn import_calendar(content: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut in_comment = false;
+    let mut pending: Option<(usize, String, String)> = None; // Entry still open for continuation lines
+
+    for (idx, raw_line) in content.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = strip_comments(raw_line, &mut in_comment);
+        if line.trim().is_empty() { continue; }
+
+        // Lines starting with a tab continue the description of the previous entry
+        if line.starts_with('\t') && let Some((_, _, description)) = pending.as_mut() {
+            description.push(' ');
+            description.push_str(line.trim());
+            continue;
+        }
+        if let Some(entry) = pending.take() { push_entry(&mut report, entry); }
+
+        if line.starts_with('#') || line.contains('=') && !line.contains('\t') {
+            report.skipped.push(format!("line {}: '{}' (preprocessor directives and settings are not imported)", line_no, line.trim()));
+            continue;
+        }
+        let (date_text, description) = split_date(&line);
+        pending = Some((line_no, date_text.to_string(), description.trim().to_string()));
+    }
+    if let Some(entry) = pending.take() { push_entry(&mut report, entry); }
+    report
+}
+
+fn push_entry(report: &mut ImportReport, (line_no, date_text, description): (usize, String, String)) {
+    if description.is_empty() {
+        report.skipped.push(format!("line {}: '{}' has no description", line_no, date_text));
+        return;
+    }
+    match parse_date(&date_text) {
+        Ok(rules) => {
+            for (rule_text, rule) in rules {
+                report.lines.push(EventLine { rule, rule_text, category: None, fg_color: None, bg_color: None, description: description.clone() }.to_line());
+            }
+            report.recurring += 1;
+        }
+        Err(reason) => report.skipped.push(format!("line {}: '{}' ({})", line_no, date_text, reason)),
+    }
+}
+
+// The date ends at the first tab; without one, take a leading month name and day or a single token
+fn split_date(line: &str) -> (&str, &str) {
+    if let Some((date, description)) = line.split_once('\t') {
+        return (date.trim(), description);
+    }
+    let tokens: Vec<&str> = line.split_whitespace().take(2).collect();
+    let date_tokens = if tokens.first().is_some_and(|t| month_from_name(t).is_some()) { 2 } else { 1 };
+    let mut rest = line.trim_start();
+    for _ in 0..date_tokens {
+        rest = rest.trim_start();
+        rest = &rest[rest.find(char::is_whitespace).unwrap_or(rest.len())..];
+    }
+    let date_len = line.trim_start().len() - rest.len();
+    (line.trim_start()[..date_len].trim(), rest)
+}
+
+fn strip_comments(line: &str, in_comment: &mut bool) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    loop {
+        if *in_comment {
+            match rest.find("*/") {
+                Some(end) => { rest = &rest[end + 2..]; *in_comment = false; }
+                None => return out,
+            }
+        } else {
+            match rest.find("/*") {
+                Some(start) => { out.push_str(&rest[..start]); rest = &rest[start + 2..]; *in_comment = true; }
+                None => { out.push_str(rest); break; }
+            }
+        }
+    }
+    if out.trim_start().starts_with("//") { String::new() } else { out }
+}
+
+// Translates a calendar(1) date into one or more rules; a month of '*' gives one rule per month
+fn parse_date(text: &str) -> Result<Vec<(String, Rule)>, String> {
+    let text = text.trim().trim_end_matches('*'); // '*' marks dates that move; the rules handle that
+    let lower = text.to_lowercase();
+    if let Some(offset) = lower.strip_prefix("easter") {
+        let offset = offset.trim();
+        let rule_text = format!("E{}", offset);
+        return match parse_rule(&rule_text) {
+            Ok(rule @ Rule::Easter(_)) if offset.is_empty() || offset.starts_with(['+', '-']) => Ok(vec![(rule_text, rule)]),
+            _ => Err(format!("unsupported Easter offset '{}'", offset)),
+        };
+    }
+
+    let parts: Vec<&str> = text.split(|c: char| c == '/' || c.is_whitespace()).filter(|s| !s.is_empty()).collect();
+    let [first, second] = parts[..] else {
+        return Err("expected a month and a day".to_string());
+    };
+
+    // Month first ("Jan 15", "01/15", "May Sun+2"), or day first ("15 Jan")
+    let (month_text, day_text) = if month_value(first).is_some() && !(first.parse::<u32>().is_ok() && month_from_name(second).is_some()) {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    let months: Vec<u32> = match month_value(month_text) {
+        Some(Some(month)) => vec![month],
+        Some(None) => (1..=12).collect(),
+        None => return Err(format!("unknown month '{}'", month_text)),
+    };
+
+    let day_rule = |month: u32| -> Result<String, String> {
+        if let Ok(day) = day_text.parse::<u32>() {
+            return Ok(format!("{}/{}", month, day));
+        }
+        let (weekday, n) = parse_weekday_ordinal(day_text).ok_or_else(|| format!("unsupported day '{}'", day_text))?;
+        Ok(format!("{}/{}#{}", month, weekday.num_days_from_sunday(), n))
+    };
+
+    let mut rules = Vec::new();
+    for month in &months {
+        let rule_text = day_rule(*month)?;
+        match parse_rule(&rule_text) {
+            Ok(rule) => rules.push((rule_text, rule)),
+            Err(e) if months.len() == 1 => return Err(e.kind.to_string()),
+            Err(_) => {} // Days that don't exist in every month, like */31
+        }
+    }
+    Ok(rules)
+}
+
+// Some(Some(month)) for a month name or number, Some(None) for '*' (every month)
+fn month_value(text: &str) -> Option<Option<u32>> {
+    if text == "*" { return Some(None); }
+    match text.parse::<u32>() {
+        Ok(month) => (1..=12).contains(&month).then_some(Some(month)),
+        Err(_) => month_from_name(text).map(Some),
+    }
+}
+
+// "Sun+2", "Sun-1", "SunSecond" or "SunLast"; ecal's #5 stands for the last one
+fn parse_weekday_ordinal(text: &str) -> Option<(Weekday, u32)> {
+    let lower = text.to_lowercase();
+    let split = match lower.find(['+', '-']) {
+        Some(idx) => idx,
+        None => ["first", "second", "third", "fourth", "last"].iter().find_map(|w| lower.strip_suffix(w).map(str::len))?,
+    };
+    let (name, ordinal) = lower.split_at(split);
+    let weekday = weekday_from_name(name)?;
+    let n = match ordinal {
+        "+1" | "first" => 1,
+        "+2" | "second" => 2,
+        "+3" | "third" => 3,
+        "+4" | "fourth" => 4,
+        "-1" | "last" => 5,
+        _ => return None,
+    };
+    Some((weekday, n))
+}
+
+// Writes every rule as a calendar(1) line; rules calendar cannot express are kept as comments
+pub fn export_calendar(rules: &RuleSet) -> String {
+    let today = chrono::Local::now().naive_local().date();
+    let mut out = format!("/* Exported from ecal on {} */\n", today.format("%Y-%m-%d"));
+
+    for (source, line_no, event_line) in &rules.event_lines {
+        let is_counting = event_line.category.as_ref().and_then(|c| rules.categories.get(c)).is_some_and(|d| d.count_format.is_some());
+        let mut description = event_line.description.replace('\t', " ");
+        let date = match event_line.rule {
+            // Birthdays and anniversaries recur every year; keep the original year in the text
+            Rule::Fixed(date) if is_counting => {
+                description.push_str(&format!(" ({})", date.year()));
+                format!("{:02}/{:02}", date.month(), date.day())
+            }
+            Rule::Easter(0) => "Easter".to_string(),
+            Rule::Easter(offset) => format!("Easter{:+}", offset),
+            Rule::NthWeekday { month, weekday, n: 5 } => format!("{}/{}-1", short_month(month), short_weekday(weekday)),
+            Rule::NthWeekday { month, weekday, n } => format!("{}/{}+{}", short_month(month), short_weekday(weekday), n),
+            Rule::Annual { month, day } => format!("{:02}/{:02}", month, day),
+            Rule::Fixed(_) | Rule::InYear { .. } | Rule::IfWeekday { .. } => {
+                out.push_str(&format!("/* {}:{}: '{}' ({}) has no calendar(1) equivalent */\n", source, line_no, event_line.rule_text, description.replace("*/", "* /")));
+                continue;
+            }
+        };
+        out.push_str(&format!("{}\t{}\n", date, description));
+    }
+    out
+}
@@ -0,0 +1,141 @@
+// CSV export of expanded events and CSV import with a configurable column mapping.
+
+use crate::parser::{EventLine, Rule, normalize_category};
+use crate::{Event, ImportReport, anniversary_number};
+use chrono::NaiveDate;
+
+// Columns are given by header name (case-insensitive) or 1-based number
+#[derive(Debug, Clone)]
+pub struct CsvMapping {
+    pub date_column: String,
+    pub date_format: String,
+    pub description_column: String,
+    pub category_column: Option<String>, // None uses a "category" column when there is one
+    pub delimiter: char,
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        CsvMapping {
+            date_column: "date".to_string(),
+            date_format: "%Y-%m-%d".to_string(),
+            description_column: "description".to_string(),
+            category_column: None,
+            delimiter: ',',
+        }
+    }
+}
+
+pub fn export_csv(events: &[Event]) -> String {
+    let mut out = String::from("date,description,category,label,fg_color,bg_color,anniversary,rule,source,line\n");
+    for event in events {
+        let fields = [
+            event.date.format("%Y-%m-%d").to_string(),
+            event.description.clone(),
+            event.category.clone().unwrap_or_default(),
+            event.label.clone().unwrap_or_default(),
+            event.fg_color.clone().unwrap_or_default(),
+            event.bg_color.clone().unwrap_or_default(),
+            anniversary_number(event).map(|n| n.to_string()).unwrap_or_default(),
+            event.rule_text.clone(),
+            event.source.clone(),
+            event.line.to_string(),
+        ];
+        let row: Vec<String> = fields.iter().map(|f| quote_field(f, ',')).collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn quote_field(field: &str, delimiter: char) -> String {
+    if field.contains([delimiter, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn import_csv(content: &str, mapping: &CsvMapping) -> Result<ImportReport, String> {
+    let mut report = ImportReport::default();
+    let rows = parse_rows(content, mapping.delimiter);
+    let Some(first_row) = rows.first() else { return Ok(report) };
+
+    // The first row is a header unless its date column already holds a date
+    let numeric_date_column = column_number(&mapping.date_column);
+    let has_header = numeric_date_column
+        .and_then(|idx| first_row.get(idx))
+        .is_none_or(|value| NaiveDate::parse_from_str(value.trim(), &mapping.date_format).is_err());
+    let header: &[String] = if has_header { first_row } else { &[] };
+
+    let date_idx = resolve_column(header, &mapping.date_column)?;
+    let description_idx = resolve_column(header, &mapping.description_column)?;
+    let category_idx = match &mapping.category_column {
+        Some(column) => Some(resolve_column(header, column)?),
+        None => resolve_column(header, "category").ok(),
+    };
+
+    let data_start = if has_header { 1 } else { 0 };
+    for (row_idx, row) in rows.iter().enumerate().skip(data_start) {
+        let row_number = row_idx + 1;
+        if row.iter().all(|f| f.trim().is_empty()) { continue; }
+
+        let date_text = row.get(date_idx).map(|s| s.trim()).unwrap_or("");
+        let Ok(date) = NaiveDate::parse_from_str(date_text, &mapping.date_format) else {
+            report.skipped.push(format!("row {}: '{}' does not match date format '{}'", row_number, date_text, mapping.date_format));
+            continue;
+        };
+        let description = row.get(description_idx).map(|s| s.split_whitespace().collect::<Vec<_>>().join(" ")).unwrap_or_default();
+        if description.is_empty() {
+            report.skipped.push(format!("row {}: empty description", row_number));
+            continue;
+        }
+        let category = category_idx.and_then(|idx| row.get(idx)).and_then(|c| normalize_category(c));
+
+        let rule_text = date.format("%d-%m-%Y").to_string();
+        let line = EventLine { rule: Rule::Fixed(date), rule_text, category, fg_color: None, bg_color: None, description };
+        report.lines.push(line.to_line());
+        report.one_off += 1;
+    }
+    Ok(report)
+}
+
+fn column_number(column: &str) -> Option<usize> {
+    column.parse::<usize>().ok().filter(|n| *n > 0).map(|n| n - 1)
+}
+
+fn resolve_column(header: &[String], column: &str) -> Result<usize, String> {
+    if let Some(idx) = column_number(column) {
+        return Ok(idx);
+    }
+    header.iter().position(|h| h.trim().eq_ignore_ascii_case(column))
+        .ok_or_else(|| format!("CSV column '{}' not found (columns: {})", column, header.join(", ")))
+}
+
+// Splits CSV content into rows, honoring quoted fields with embedded delimiters, quotes and newlines
+fn parse_rows(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content); // Spreadsheet BOM
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => { field.push('"'); chars.next(); }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => row.push(std::mem::take(&mut field)),
+            '\r' if !in_quotes => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
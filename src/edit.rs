@@ -0,0 +1,293 @@
+// Commands that change rule lines of the events file. Lines are validated with the same parser
+// as load_events before anything is written, and the rest of the file is kept byte for byte.
+
+use crate::parser::{EventLine, Line, parse_line, parse_rule};
+use crate::store;
+use std::collections::HashMap;
+
+// Fields of a rule line given on the command line
+#[derive(Debug, Default)]
+pub struct LineFields {
+    pub rule: Option<String>,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub fg_color: Option<String>,
+    pub bg_color: Option<String>,
+}
+
+pub fn run_add(events_file: &str, fields: &LineFields, section: Option<&str>) -> Result<(), String> {
+    let (Some(rule_text), Some(description)) = (&fields.rule, &fields.description) else {
+        return Err("Usage: ecal add <RULE> <DESCRIPTION> [--category <NAME>] [--fg <COLOR>] [--bg <COLOR>] [--section <NAME>]".to_string());
+    };
+    let line = build_line(rule_text, description, fields.category.clone(), fields.fg_color.clone(), fields.bg_color.clone())?;
+
+    let mut line_no = 0;
+    store::modify(events_file, |content| {
+        let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let at = match section {
+            Some(name) => section_end(&lines, name).ok_or_else(|| format!("No section matching '{}' in '{}'.", name, events_file))?,
+            None => lines.len(),
+        };
+        let new_line = format!("{}\n", line);
+        lines.insert(at, &new_line);
+        line_no = at + 1;
+        Ok(join_lines(&lines))
+    })?;
+    println!("Added to '{}' line {}: {}", events_file, line_no, line);
+    Ok(())
+}
+
+// Builds the line text and runs it through the parser, so nothing is written that load_events would
+// reject or read differently
+fn build_line(rule_text: &str, description: &str, category: Option<String>, fg_color: Option<String>, bg_color: Option<String>) -> Result<String, String> {
+    let rule = parse_rule(rule_text).map_err(|e| format!("Invalid rule '{}': {}", rule_text, e.kind))?;
+    let description = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    let line = EventLine { rule, rule_text: rule_text.trim().to_string(), category, fg_color, bg_color, description };
+    check_meta(&line)?;
+    let text = line.to_line();
+    validate(&text, &line)?;
+    Ok(text)
+}
+
+// The meta block is split at ',' and ends at ']', so those can't be part of a category or color
+fn check_meta(line: &EventLine) -> Result<(), String> {
+    for (field, value) in [("category", &line.category), ("foreground color", &line.fg_color), ("background color", &line.bg_color)] {
+        if let Some(value) = value && value.contains([',', ';', '[', ']']) {
+            return Err(format!("Invalid {} '{}': it cannot contain ',', ';', '[' or ']'", field, value));
+        }
+    }
+    Ok(())
+}
+
+// Checks that the text parses without errors and reads back as exactly the intended line
+fn validate(text: &str, intended: &EventLine) -> Result<(), String> {
+    match parse_line(text) {
+        (Line::Event(event_line), errors) if errors.is_empty() && event_line == *intended => Ok(()),
+        (Line::Event(_), errors) if errors.is_empty() => Err(format!("Invalid line '{}': it would not read back as given", text)),
+        (_, errors) => Err(format!("Invalid line '{}': {}", text, errors.first().map_or("not an event".to_string(), |e| e.kind.to_string()))),
+    }
+}
+
+pub fn run_list(events_file: &str, show_ids: bool) -> Result<(), String> {
+    let content = std::fs::read_to_string(events_file).map_err(|e| format!("Cannot read '{}': {}", events_file, e))?;
+    let lines: Vec<&str> = content.lines().collect();
+    for (id, idx, _) in rule_lines(&content) {
+        let line = lines[idx].trim_end();
+        if show_ids {
+            println!("\x1b[33m{:<9}\x1b[0m {:>4}  {}", id, idx + 1, line);
+        } else {
+            println!("{:>4}  {}", idx + 1, line);
+        }
+    }
+    if show_ids {
+        println!("Editing the rule, category or description of a line changes its id; edit prints the new one.");
+    }
+    Ok(())
+}
+
+pub fn run_remove(events_file: &str, id: &str) -> Result<(), String> {
+    let mut removed = (0, String::new());
+    store::modify(events_file, |content| {
+        let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let (_, idx, _) = find_rule_line(&rule_lines(content), id)?;
+        removed = (idx + 1, lines[idx].trim_end().to_string());
+        lines.remove(idx);
+        Ok(join_lines(&lines))
+    })?;
+    println!("Removed from '{}' line {}: {}", events_file, removed.0, removed.1);
+    Ok(())
+}
+
+pub fn run_edit(events_file: &str, id: &str, fields: &LineFields) -> Result<(), String> {
+    if fields.rule.is_none() && fields.description.is_none() && fields.category.is_none() && fields.fg_color.is_none() && fields.bg_color.is_none() {
+        return Err("Usage: ecal edit <ID> [--rule <RULE>] [--desc <DESCRIPTION>] [--category <NAME>] [--fg <COLOR>] [--bg <COLOR>]".to_string());
+    }
+    let mut edited = (0, String::new(), String::new(), String::new());
+    store::modify(events_file, |content| {
+        let mut lines: Vec<&str> = content.split_inclusive('\n').collect();
+        let (_, idx, old) = find_rule_line(&rule_lines(content), id)?;
+        let new = edited_event_line(&old, fields)?;
+        let original = lines[idx];
+        let ending = &original[original.trim_end_matches(['\r', '\n']).len()..];
+        let mut text = rewrite_line(original.trim_end_matches(['\r', '\n']), &old, &new);
+        if validate(&text, &new).is_err() {
+            text = new.to_line();
+            validate(&text, &new)?;
+        }
+        let new_line = format!("{}{}", text, ending);
+        lines[idx] = &new_line;
+        let new_content = join_lines(&lines);
+        let new_id = rule_lines(&new_content).into_iter().find(|(_, i, _)| *i == idx).map(|(id, _, _)| id).unwrap_or_default();
+        edited = (idx + 1, original.trim_end().to_string(), text, new_id);
+        Ok(new_content)
+    })?;
+    println!("Changed '{}' line {} (id now {}):\n  - {}\n  + {}", events_file, edited.0, edited.3, edited.1, edited.2);
+    Ok(())
+}
+
+// The rule lines of the content with their ids and 0-based line indexes. An id is a hash of the rule,
+// category and description, so it survives edits elsewhere in the file and reformatting of the line;
+// identical lines are told apart by a -2, -3... suffix in file order. Editing the rule, category or
+// description of a line therefore gives it a new id, which run_edit prints
+fn rule_lines(content: &str) -> Vec<(String, usize, EventLine)> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut out = Vec::new();
+    for (idx, text) in content.lines().enumerate() {
+        let (Line::Event(event_line), _) = parse_line(text) else { continue };
+        let key = format!("{};{};{}", event_line.rule_text, event_line.category.as_deref().unwrap_or(""), event_line.description);
+        let hash = format!("{:07x}", crate::fnv1a_hash(&key) >> 36);
+        let count = seen.entry(hash.clone()).or_insert(0);
+        *count += 1;
+        let id = if *count == 1 { hash } else { format!("{}-{}", hash, count) };
+        out.push((id, idx, event_line));
+    }
+    out
+}
+
+// Finds a line by its id or by an unambiguous prefix of it
+fn find_rule_line(rules: &[(String, usize, EventLine)], id: &str) -> Result<(String, usize, EventLine), String> {
+    let id = id.to_lowercase();
+    if let Some(found) = rules.iter().find(|(rule_id, _, _)| *rule_id == id) {
+        return Ok(found.clone());
+    }
+    let matches: Vec<_> = rules.iter().filter(|(rule_id, _, _)| rule_id.starts_with(&id)).collect();
+    match matches.as_slice() {
+        [found] => Ok((*found).clone()),
+        [] => Err(format!("No rule line with id '{}'; 'ecal list --ids' shows them.", id)),
+        _ => Err(format!("Id '{}' matches {} rule lines; give more of it.", id, matches.len())),
+    }
+}
+
+// Applies the changed fields to a line; an empty category or color removes it
+fn edited_event_line(old: &EventLine, fields: &LineFields) -> Result<EventLine, String> {
+    let mut new = old.clone();
+    if let Some(rule_text) = &fields.rule {
+        new.rule = parse_rule(rule_text).map_err(|e| format!("Invalid rule '{}': {}", rule_text, e.kind))?;
+        new.rule_text = rule_text.trim().to_string();
+    }
+    if let Some(description) = &fields.description {
+        new.description = description.split_whitespace().collect::<Vec<_>>().join(" ");
+    }
+    for (field, slot) in [(&fields.category, &mut new.category), (&fields.fg_color, &mut new.fg_color), (&fields.bg_color, &mut new.bg_color)] {
+        if let Some(value) = field {
+            *slot = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+        }
+    }
+    check_meta(&new)?;
+    Ok(new)
+}
+
+// Changes only the parts of the line that differ: the rule keeps its column and the ';' stays where
+// it was if the new rule fits, the meta block is rebuilt only when category or colors change
+fn rewrite_line(original: &str, old: &EventLine, new: &EventLine) -> String {
+    let Some((head, tail)) = original.split_once(';') else { return new.to_line() };
+
+    let head = if new.rule_text == old.rule_text {
+        head.to_string()
+    } else {
+        let indent = &head[..head.len() - head.trim_start().len()];
+        let padded = head.trim_end().len() < head.len();
+        let width = head.chars().count();
+        let mut head = format!("{}{}", indent, new.rule_text);
+        if padded {
+            head.push(' ');
+            while head.chars().count() < width { head.push(' '); }
+        }
+        head
+    };
+
+    let rest = tail.trim_start();
+    let meta_end = rest.strip_prefix('[').and_then(|r| r.find(']')).map_or(0, |end| end + 2);
+    let description = rest[meta_end..].trim_start();
+    let meta = &rest[..rest.len() - description.len()];
+    let meta = if (&new.category, &new.fg_color, &new.bg_color) == (&old.category, &old.fg_color, &old.bg_color) {
+        format!("{}{}", &tail[..tail.len() - rest.len()], meta)
+    } else {
+        new.meta_block()
+    };
+    let description = if new.description == old.description { description } else { new.description.as_str() };
+    let spacing = if meta.is_empty() || meta.ends_with(char::is_whitespace) { "" } else { " " };
+    format!("{};{}{}{}", head, meta, spacing, description).trim_end().to_string()
+}
+
+// Index after the last line of the first section whose header comment mentions `name`: the
+// section runs from its header to the next blank line after its rules
+fn section_end(lines: &[&str], name: &str) -> Option<usize> {
+    let name = name.to_lowercase();
+    let header = lines.iter().position(|l| l.trim_start().starts_with('#') && l.to_lowercase().contains(&name))?;
+    let mut idx = header + 1;
+    while lines.get(idx).is_some_and(|l| l.trim_start().starts_with('#')) { idx += 1; }
+    while lines.get(idx).is_some_and(|l| !l.trim().is_empty()) { idx += 1; }
+    Some(idx)
+}
+
+// Joins lines that keep their own line endings, ending a last line that had none
+fn join_lines(lines: &[&str]) -> String {
+    let mut out = String::new();
+    for line in lines {
+        if !out.is_empty() && !out.ends_with('\n') { out.push('\n'); }
+        out.push_str(line);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(category: Option<&str>, fg_color: Option<&str>, description: Option<&str>) -> LineFields {
+        LineFields {
+            description: description.map(str::to_string),
+            category: category.map(str::to_string),
+            fg_color: fg_color.map(str::to_string),
+            ..LineFields::default()
+        }
+    }
+
+    fn event_line(text: &str) -> EventLine {
+        match parse_line(text) {
+            (Line::Event(event_line), errors) if errors.is_empty() => event_line,
+            other => panic!("'{}' is not a valid event line: {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn built_lines_read_back_as_given() {
+        let cases = [
+            ("12/25", "Christmas", None, None, None),
+            ("E-2", "Good Friday", Some("church"), Some("red"), None),
+            ("11/4#4", "[US] Thanksgiving", None, None, Some("yellow")),
+            ("01-01-1950", "🎂 Joe; the neighbour", Some("bday"), None, Some("blue")),
+        ];
+        for (rule, description, category, fg, bg) in cases {
+            let text = build_line(rule, description, category.map(str::to_string), fg.map(str::to_string), bg.map(str::to_string)).unwrap();
+            let line = event_line(&text);
+            assert_eq!((line.rule_text.as_str(), line.description.as_str()), (rule, description), "{}", text);
+            assert_eq!((line.category.as_deref(), line.fg_color.as_deref(), line.bg_color.as_deref()), (category, fg, bg), "{}", text);
+        }
+    }
+
+    #[test]
+    fn meta_values_that_would_not_read_back_are_rejected() {
+        for category in ["a]b", "a,b", "a;b", "[a"] {
+            assert!(build_line("1/1", "New Year", Some(category.to_string()), None, None).is_err(), "{}", category);
+        }
+        assert!(build_line("1/1", "New Year", None, Some("red, blue".to_string()), None).is_err());
+
+        let old = event_line("1/1 ;[ie] New Year");
+        assert!(edited_event_line(&old, &fields(Some("a]b"), None, None)).is_err());
+        assert!(edited_event_line(&old, &fields(None, Some("x;y"), None)).is_err());
+    }
+
+    #[test]
+    fn edited_lines_read_back_as_given() {
+        let original = "1/1       ;[ie, red] New Year   ";
+        let old = event_line(original);
+        for (category, fg, description) in [(Some(""), None, None), (None, Some(""), None), (Some("de"), Some("blue"), Some("[Neujahr]")), (None, None, Some("New Year's Day"))] {
+            let new = edited_event_line(&old, &fields(category, fg, description)).unwrap();
+            let text = rewrite_line(original, &old, &new);
+            let text = if validate(&text, &new).is_ok() { text } else { new.to_line() };
+            assert_eq!(validate(&text, &new), Ok(()), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,338 @@
+// Conversion between the rules and Emacs: diary files with sexp entries (diary-date, diary-float,
+// diary-anniversary) and Org files with timestamps and repeaters. Diary dates are written and read
+// in the default american calendar-date-style (month, day, year).
+
+use crate::parser::{EventLine, Rule, normalize_category, parse_rule};
+use crate::{ImportReport, RuleSet, month_from_name};
+use chrono::{Datelike, NaiveDate};
+
+// The diary sexp for a rule, and whether the description should count years (%d)
+fn rule_sexp(rule: Rule, counting: bool) -> String {
+    match rule {
+        Rule::Fixed(date) if counting => format!("(diary-anniversary {} {} {})", date.month(), date.day(), date.year()),
+        Rule::Fixed(date) => format!("(diary-date {} {} {})", date.month(), date.day(), date.year()),
+        Rule::Annual { month, day } => format!("(diary-date {} {} t)", month, day),
+        Rule::InYear { month, day, year } => format!("(diary-date {} {} {})", month, day, year),
+        Rule::NthWeekday { month, weekday, n } => format!("(diary-float {} {} {})", month, weekday.num_days_from_sunday(), if n == 5 { -1 } else { n as i32 }),
+        // holiday-easter-etc only looks at the months around displayed-month, so show it the date's month
+        Rule::Easter(offset) => format!(
+            "(let ((displayed-month (calendar-extract-month date)) (displayed-year (calendar-extract-year date))) (assoc date (holiday-easter-etc {} \"\")))",
+            offset
+        ),
+        Rule::IfWeekday { month, day, weekday, offset } => format!(
+            "(let ((base (calendar-gregorian-from-absolute (- (calendar-absolute-from-gregorian date) {})))) (and (= (calendar-extract-month base) {}) (= (calendar-extract-day base) {}) (= (calendar-day-of-week base) {})))",
+            offset, month, day, weekday.num_days_from_sunday()
+        ),
+    }
+}
+
+// The count text of a counting category in diary-anniversary terms: %d years, %s its ordinal suffix
+fn diary_count(count_format: &str) -> String {
+    count_format.replace('%', "%%").replace("{nth}", "%d%s").replace("{n}", "%d")
+}
+
+pub fn export_diary(rules: &RuleSet) -> String {
+    let today = chrono::Local::now().naive_local().date();
+    let mut out = format!(";; Exported from ecal on {} (calendar-date-style: american)\n", today.format("%Y-%m-%d"));
+    for (_, _, event_line) in &rules.event_lines {
+        let count_format = event_line.category.as_ref().and_then(|c| rules.categories.get(c)).and_then(|d| d.count_format.as_deref());
+        let mut description = event_line.description.replace('%', "%%");
+        if let (Rule::Fixed(_), Some(count_format)) = (event_line.rule, count_format) {
+            description.push_str(&format!(" ({})", diary_count(count_format)));
+        }
+        out.push_str(&format!("%%{} {}\n", rule_sexp(event_line.rule, count_format.is_some()), description));
+    }
+    out
+}
+
+// Simple dates become timestamps (with a yearly repeater for annual rules), the rest diary sexps
+pub fn export_org(rules: &RuleSet, year: i32) -> String {
+    let today = chrono::Local::now().naive_local().date();
+    let mut out = format!("#+TITLE: Events\n# Exported from ecal on {}\n", today.format("%Y-%m-%d"));
+    for (_, _, event_line) in &rules.event_lines {
+        let count_format = event_line.category.as_ref().and_then(|c| rules.categories.get(c)).and_then(|d| d.count_format.as_deref());
+        let headline = &event_line.description;
+        let timestamp = match (event_line.rule, count_format) {
+            (Rule::Fixed(date), None) => org_timestamp(date, ""),
+            // Org substitutes the years only in sexp entries of their own
+            (Rule::Fixed(date), Some(count_format)) => {
+                format!("%%{} {} ({})", rule_sexp(Rule::Fixed(date), true), event_line.description.replace('%', "%%"), diary_count(count_format))
+            }
+            // The first occurrence from the start year on carries the repeater (Feb 29 needs a leap year)
+            (Rule::Annual { month, day }, _) => match (year..year + 8).find_map(|y| NaiveDate::from_ymd_opt(y, month, day)) {
+                Some(date) => org_timestamp(date, " +1y"),
+                None => continue,
+            },
+            (Rule::InYear { month, day, year }, _) => match NaiveDate::from_ymd_opt(year, month, day) {
+                Some(date) => org_timestamp(date, ""),
+                None => continue,
+            },
+            (rule, _) => format!("<%%{}>", rule_sexp(rule, false)),
+        };
+        // Org tags allow letters, digits, _, @, # and %
+        let tags = event_line.category.as_ref().map(|c| format!(" :{}:", c.replace(|ch: char| !ch.is_alphanumeric() && !"_@#%".contains(ch), "_"))).unwrap_or_default();
+        out.push_str(&format!("* {}{}\n  {}\n", headline, tags, timestamp));
+    }
+    out
+}
+
+fn org_timestamp(date: NaiveDate, repeater: &str) -> String {
+    format!("<{}{}>", date.format("%Y-%m-%d %a"), repeater)
+}
+
+pub fn import_diary(content: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    // Entries continue on lines starting with whitespace
+    let mut entries: Vec<(usize, String)> = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        if line.starts_with([' ', '\t']) && let Some((_, entry)) = entries.last_mut() {
+            entry.push(' ');
+            entry.push_str(line.trim());
+        } else if !line.trim().is_empty() && !line.starts_with(';') {
+            entries.push((idx + 1, line.to_string()));
+        }
+    }
+
+    for (line_no, entry) in entries {
+        let entry = entry.strip_prefix('&').unwrap_or(&entry); // Nonmarking entries
+        let result = match entry.strip_prefix("%%") {
+            Some(sexp_entry) => split_sexp(sexp_entry).ok_or_else(|| "unbalanced parentheses".to_string())
+                .and_then(|(sexp, text)| sexp_rules(sexp).map(|converted| (converted, text.to_string()))),
+            None => diary_date(entry).map(|(rules, text)| ((rules, None), text.to_string())),
+        };
+        match result {
+            Ok((converted, text)) => push_rules(&mut report, converted, None, &text, line_no),
+            Err(reason) => report.skipped.push(format!("line {}: '{}' ({})", line_no, entry.trim(), reason)),
+        }
+    }
+    report
+}
+
+pub fn import_org(content: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut heading: Option<(usize, String, Option<String>)> = None; // Line, title and category of the current entry
+    let mut found_timestamp = false;
+
+    for (idx, line) in content.lines().enumerate() {
+        let line_no = idx + 1;
+        if line.starts_with('*') && line.trim_start_matches('*').starts_with(' ') {
+            let (title, category) = parse_headline(line.trim_start_matches('*').trim());
+            heading = Some((line_no, title, category));
+            found_timestamp = false;
+        }
+        // Diary sexp entries may stand on their own lines in Org files too, dating the entry they are in
+        if let Some(sexp_entry) = line.trim_start().strip_prefix("%%") {
+            found_timestamp = true;
+            let category = heading.as_ref().and_then(|(_, _, category)| category.clone());
+            match split_sexp(sexp_entry).ok_or_else(|| "unbalanced parentheses".to_string()).and_then(|(sexp, text)| sexp_rules(sexp).map(|c| (c, text))) {
+                Ok((converted, text)) => push_rules(&mut report, converted, category, text, line_no),
+                Err(reason) => report.skipped.push(format!("line {}: '{}' ({})", line_no, line.trim(), reason)),
+            }
+            continue;
+        }
+        let Some((heading_line, title, category)) = heading.as_ref() else { continue };
+        if found_timestamp { continue; }
+        // The first active timestamp of an entry dates it
+        let Some(start) = line.find('<') else { continue };
+        let Some(end) = line[start..].find('>') else { continue };
+        found_timestamp = true;
+        let timestamp = &line[start + 1..start + end];
+        let result = match timestamp.strip_prefix("%%") {
+            Some(sexp) => split_sexp(sexp).ok_or_else(|| "unbalanced parentheses".to_string()).and_then(|(sexp, _)| sexp_rules(sexp)),
+            None => org_rules(timestamp).map(|rules| (rules, None)),
+        };
+        match result {
+            Ok(converted) => push_rules(&mut report, converted, category.clone(), title, *heading_line),
+            Err(reason) => report.skipped.push(format!("line {}: '{}' <{}> ({})", heading_line, title, timestamp, reason)),
+        }
+    }
+    report
+}
+
+type Rules = Vec<(String, Rule)>;
+type Converted = (Rules, Option<String>); // Rules and the category they imply
+
+fn push_rules(report: &mut ImportReport, (rules, implied_category): Converted, category: Option<String>, text: &str, line_no: usize) {
+    let description = clean_text(text);
+    if description.is_empty() {
+        report.skipped.push(format!("line {}: entry without text", line_no));
+        return;
+    }
+    // diary-anniversary entries are birthdays when they say so
+    let implied_category = implied_category.map(|c| if text.to_lowercase().contains("birthday") { "bday".to_string() } else { c });
+    let category = category.or(implied_category);
+    if rules.iter().all(|(_, rule)| matches!(rule, Rule::Fixed(_))) && category.is_none() { report.one_off += 1; } else { report.recurring += 1; }
+    for (rule_text, rule) in rules {
+        let line = EventLine { rule, rule_text, category: category.clone(), fg_color: None, bg_color: None, description: description.clone() };
+        report.lines.push(line.to_line());
+    }
+}
+
+// Drops diary-anniversary count placeholders: "(%d%s Birthday)" groups and any lone %d or %s
+fn clean_text(text: &str) -> String {
+    let mut text = text.to_string();
+    while let Some(pos) = text.find("%d") {
+        let open = text[..pos].rfind('(');
+        let close = text[pos..].find(')').map(|c| pos + c);
+        match (open, close) {
+            (Some(open), Some(close)) => text.replace_range(open..=close, ""),
+            _ => text.replace_range(pos..pos + 2, ""),
+        }
+    }
+    text.replace("%s", "").replace("%%", "%").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Splits "(sexp ...) text" into the sexp and the text after it
+fn split_sexp(entry: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    let mut in_string = false;
+    for (pos, c) in entry.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '(' if !in_string => depth += 1,
+            ')' if !in_string => {
+                depth -= 1;
+                if depth == 0 { return Some((&entry[..=pos], &entry[pos + 1..])); }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn sexp_rules(sexp: &str) -> Result<Converted, String> {
+    let inner = sexp.trim().strip_prefix('(').and_then(|s| s.strip_suffix(')')).ok_or("not a sexp")?;
+    let mut words = inner.split_whitespace();
+    let function = words.next().unwrap_or("");
+    let args: Vec<&str> = words.collect();
+    let number = |idx: usize| args.get(idx).and_then(|a| a.parse::<i64>().ok());
+    let any = |idx: usize| args.get(idx) == Some(&"t");
+
+    let rule_texts: Vec<String> = match function {
+        "diary-date" if args.len() >= 3 => match (number(0), number(1), number(2), any(0), any(2)) {
+            (Some(month), Some(day), None, _, true) => vec![format!("{}/{}", month, day)],
+            (Some(month), Some(day), Some(year), _, _) => {
+                let date = NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32).ok_or("invalid date")?;
+                vec![date.format("%d-%m-%Y").to_string()]
+            }
+            (None, Some(day), None, true, true) => (1..=12).map(|m| format!("{}/{}", m, day)).filter(|r| parse_rule(r).is_ok()).collect(),
+            _ => return Err(format!("unsupported {}", sexp)),
+        },
+        "diary-float" if args.len() == 3 => {
+            let (dow, n) = (number(1).ok_or("unsupported day of week")?, number(2).ok_or("unsupported occurrence")?);
+            let n = match n { 1..=4 => n, -1 => 5, _ => return Err(format!("occurrence {} has no ecal equivalent", n)) };
+            let months: Vec<i64> = match number(0) { Some(month) => vec![month], None if any(0) => (1..=12).collect(), None => return Err(format!("unsupported {}", sexp)) };
+            months.iter().map(|m| format!("{}/{}#{}", m, dow, n)).collect()
+        }
+        "diary-anniversary" if args.len() == 3 => {
+            let (month, day, year) = (number(0).ok_or("invalid month")?, number(1).ok_or("invalid day")?, number(2).ok_or("invalid year")?);
+            let date = NaiveDate::from_ymd_opt(year as i32, month as u32, day as u32).ok_or("invalid date")?;
+            let rule = (date.format("%d-%m-%Y").to_string(), Rule::Fixed(date));
+            return Ok((vec![rule], Some("anni".to_string())));
+        }
+        "let" if let Some(rule_text) = if_weekday_rule(inner) => vec![rule_text],
+        _ if inner.contains("holiday-easter-etc") => {
+            let offset = inner.split("holiday-easter-etc").nth(1).and_then(|rest| rest.split_whitespace().next())
+                .and_then(|n| n.trim_end_matches(')').parse::<i64>().ok()).unwrap_or(0);
+            vec![if offset == 0 { "E".to_string() } else { format!("E{:+}", offset) }]
+        }
+        _ => return Err(format!("{} has no ecal equivalent", function)),
+    };
+    let mut rules = Vec::new();
+    for rule_text in rule_texts {
+        let rule = parse_rule(&rule_text).map_err(|e| e.kind.to_string())?;
+        rules.push((rule_text, rule));
+    }
+    if rules.is_empty() { return Err("no valid date".to_string()); }
+    Ok((rules, None))
+}
+
+// The sexp export_diary writes for "MM/DD?D+N" rules
+fn if_weekday_rule(inner: &str) -> Option<String> {
+    let number_after = |marker: &str| inner.split(marker).nth(1)?.split(')').next()?.trim().parse::<i64>().ok();
+    let offset = number_after("(calendar-absolute-from-gregorian date)")?;
+    let month = number_after("(calendar-extract-month base)")?;
+    let day = number_after("(calendar-extract-day base)")?;
+    let weekday = number_after("(calendar-day-of-week base)")?;
+    Some(format!("{}/{}?{}{}{}", month, day, weekday, if offset < 0 { '-' } else { '+' }, offset.unsigned_abs()))
+}
+
+// Plain diary dates: 3/17, 3/17/2026, March 17, March 17, 2026 (a '*' matches any)
+fn diary_date(entry: &str) -> Result<(Rules, &str), String> {
+    let mut words = entry.splitn(2, char::is_whitespace);
+    let first = words.next().unwrap_or("");
+    let rest = words.next().unwrap_or("");
+
+    let (month, day, year, text) = if first.contains('/') {
+        let parts: Vec<&str> = first.split('/').collect();
+        match parts[..] {
+            [month, day] => (month, day, None, rest),
+            [month, day, year] => (month, day, Some(year), rest),
+            _ => return Err("unsupported date".to_string()),
+        }
+    } else if let Some(month) = month_from_name(first) {
+        let mut words = rest.splitn(2, char::is_whitespace);
+        let day = words.next().unwrap_or("");
+        let after_day = words.next().unwrap_or("");
+        if let Some(day) = day.strip_suffix(',') {
+            let mut words = after_day.splitn(2, char::is_whitespace);
+            let year = words.next().unwrap_or("");
+            (month_number(month), day, Some(year), words.next().unwrap_or(""))
+        } else {
+            (month_number(month), day, None, after_day)
+        }
+    } else {
+        return Err("weekly and other entries have no ecal equivalent".to_string());
+    };
+
+    let month_text = month.to_string();
+    let rule_text = match year {
+        Some(year) if year != "*" => format!("{}/{}/{}", month_text, day, year),
+        _ if month_text == "*" => return Err("monthly entries are not imported".to_string()),
+        _ => format!("{}/{}", month_text, day),
+    };
+    let rule = parse_rule(&rule_text).map_err(|e| e.kind.to_string())?;
+    // Full dates are written as DD-MM-YYYY like the other importers
+    let rule_text = match rule { Rule::Fixed(date) => date.format("%d-%m-%Y").to_string(), _ => rule_text };
+    Ok((vec![(rule_text, rule)], text))
+}
+
+fn month_number(month: u32) -> &'static str {
+    ["1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12"][month as usize - 1]
+}
+
+// Org timestamp contents: "2026-03-17 Tue", optionally with a time and a repeater like +1y
+fn org_rules(timestamp: &str) -> Result<Rules, String> {
+    let mut parts = timestamp.split_whitespace();
+    let date = parts.next().and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()).ok_or("invalid date")?;
+    let repeater = parts.find(|p| p.starts_with(['+', '.']));
+    let rule_texts = match repeater.map(|r| r.trim_start_matches(['+', '.'])) {
+        None => vec![date.format("%d-%m-%Y").to_string()],
+        Some("1y") => vec![format!("{}/{}", date.month(), date.day())],
+        Some("1m") => (1..=12).map(|m| format!("{}/{}", m, date.day())).filter(|r| parse_rule(r).is_ok()).collect(),
+        Some(other) => return Err(format!("repeater +{} has no ecal equivalent", other)),
+    };
+    rule_texts.into_iter().map(|rule_text| parse_rule(&rule_text).map(|rule| (rule_text, rule)).map_err(|e| e.kind.to_string())).collect()
+}
+
+// The headline text without TODO keywords, priority cookies, timestamps and tags; the first tag is the category
+fn parse_headline(headline: &str) -> (String, Option<String>) {
+    let mut title = headline.trim().to_string();
+    let mut category = None;
+    if let Some(pos) = title.trim_end().rfind(char::is_whitespace) {
+        let last = title[pos..].trim();
+        if last.len() > 2 && last.starts_with(':') && last.ends_with(':') {
+            category = last.trim_matches(':').split(':').next().and_then(normalize_category);
+            title.truncate(pos);
+        }
+    }
+    for keyword in ["TODO ", "DONE "] {
+        if let Some(rest) = title.strip_prefix(keyword) { title = rest.to_string(); }
+    }
+    if title.starts_with("[#") && let Some(end) = title.find(']') { title = title[end + 1..].to_string(); }
+    while let (Some(start), Some(end)) = (title.find('<'), title.find('>')) {
+        if end < start { break; }
+        title.replace_range(start..=end, "");
+    }
+    (title.split_whitespace().collect::<Vec<_>>().join(" "), category)
+}
@@ -0,0 +1,74 @@
+// Searching the events by text. Every rule whose description, category or category label matches
+// is expanded over a window of years around today, and its previous and next occurrences shown.
+
+use crate::{Config, Event, category_selected, format_event_entry, load_events};
+use chrono::Datelike;
+use regex::{Regex, RegexBuilder};
+
+pub const DEFAULT_YEARS: i32 = 2;
+
+enum Pattern {
+    Text(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn matches(&self, text: &str) -> bool {
+        match self {
+            Pattern::Text(needle) => text.to_lowercase().contains(needle),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+pub fn run_find(events_file: &str, config: &Config, pattern: &str, use_regex: bool, years: i32) -> Result<(), String> {
+    let pattern = if use_regex {
+        Pattern::Regex(RegexBuilder::new(pattern).case_insensitive(true).build().map_err(|e| format!("Invalid regex '{}': {}", pattern, e))?)
+    } else {
+        Pattern::Text(pattern.to_lowercase())
+    };
+
+    let today = chrono::Local::now().naive_local().date();
+    let window = Config {
+        start_year: today.year() - years,
+        start_month: 1,
+        num_months: (2 * years as usize + 1) * 12,
+        from: None,
+        to: None,
+        window: None,
+        ..config.clone()
+    };
+    let events = load_events(events_file, &window);
+
+    // Occurrences grouped by the rule line that produced them, in the order the rules first occur
+    let mut hits: Vec<Vec<&Event>> = Vec::new();
+    for event in events.iter().filter(|e| category_selected(config, e)) {
+        let fields = [Some(&event.description), event.category.as_ref(), event.label.as_ref()];
+        if !fields.iter().flatten().any(|field| pattern.matches(field)) { continue; }
+        match hits.iter_mut().find(|group| group[0].source == event.source && group[0].line == event.line) {
+            Some(group) => group.push(event),
+            None => hits.push(vec![event]),
+        }
+    }
+
+    if hits.is_empty() {
+        println!("No events match between {} and {}.", window.start_year, today.year() + years);
+        return Ok(());
+    }
+    hits.sort_by_key(|group| group.iter().find(|e| e.date >= today).map_or((1, group[0].date), |e| (0, e.date)));
+
+    for group in hits {
+        let first = group[0];
+        let category = first.category.as_ref().map(|c| format!(" [{}]", c)).unwrap_or_default();
+        println!("\n\x1b[1m{}\x1b[0m{}  \x1b[90m{}:{}  {}\x1b[0m", first.description, category, first.source, first.line, first.rule_text);
+        match group.iter().rev().find(|e| e.date < today) {
+            Some(event) => println!("  previous: {}", format_event_entry(event, today, true)),
+            None => println!("  previous: none since {}", window.start_year),
+        }
+        match group.iter().find(|e| e.date >= today) {
+            Some(event) => println!("  next:     {}", format_event_entry(event, today, true)),
+            None => println!("  next:     none until {}", today.year() + years),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,166 @@
+// Canonical layout of the events file: the rules of each block aligned on one ';' column, meta
+// blocks written as "[category, fg, bg]", @category settings in a fixed order and aligned as a
+// table, comments kept, runs of blank lines collapsed to one. With sorting, the rules of each
+// block are put in calendar order.
+
+use crate::history;
+use crate::parser::{self, CategoryDef, EventLine, Line, Rule, parse_line};
+use crate::store;
+use chrono::{Datelike, NaiveDate};
+
+enum Item {
+    Blank,
+    Comment(String),
+    Category(String, CategoryDef),
+    Event(EventLine),
+}
+
+// Returns whether the file is (or, with `check`, would stay) unchanged
+pub fn run_fmt(events_file: &str, check: bool, sort: bool) -> Result<bool, String> {
+    if check {
+        let content = std::fs::read_to_string(events_file).map_err(|e| format!("Cannot read '{}': {}", events_file, e))?;
+        let formatted = format_source(events_file, &content, sort)?;
+        if formatted == content {
+            println!("'{}' is formatted.", events_file);
+            return Ok(true);
+        }
+        println!("\x1b[1m'{}' is not formatted; 'ecal fmt' would change:\x1b[0m", events_file);
+        history::print_diff(&content, &formatted);
+        return Ok(false);
+    }
+
+    let mut unchanged = true;
+    store::modify(events_file, |content| {
+        let formatted = format_source(events_file, content, sort)?;
+        unchanged = formatted == content;
+        Ok(formatted)
+    })?;
+    if unchanged {
+        println!("'{}' is already formatted.", events_file);
+    } else {
+        println!("Formatted '{}'; 'ecal undo' restores the previous layout.", events_file);
+    }
+    Ok(unchanged)
+}
+
+// Files with problems are left alone, as formatting would have to guess what the lines mean
+fn format_source(file: &str, content: &str, sort: bool) -> Result<String, String> {
+    let (_, errors) = parser::parse_source(file, content);
+    if !errors.is_empty() {
+        return Err(format!("'{}' has {} problem(s); fix them first (see 'ecal check').", file, errors.len()));
+    }
+
+    let items: Vec<Item> = content.lines().map(|text| match parse_line(text) {
+        (Line::Event(event_line), _) => Item::Event(event_line),
+        (Line::Category { name, def }, _) => Item::Category(name, def),
+        _ if text.trim().is_empty() => Item::Blank,
+        _ => Item::Comment(text.trim_end().to_string()),
+    }).collect();
+
+    let mut out: Vec<String> = Vec::new();
+    let mut idx = 0;
+    while idx < items.len() {
+        let end = idx + items[idx..].iter().take_while(|item| same_kind(item, &items[idx])).count();
+        match &items[idx] {
+            Item::Blank => if !out.is_empty() { out.push(String::new()) },
+            Item::Comment(_) => out.extend(items[idx..end].iter().filter_map(|item| match item {
+                Item::Comment(text) => Some(text.clone()),
+                _ => None,
+            })),
+            Item::Category(..) => out.extend(format_categories(&items[idx..end])),
+            Item::Event(_) => {
+                let mut rules: Vec<&EventLine> = items[idx..end].iter().filter_map(|item| match item {
+                    Item::Event(event_line) => Some(event_line),
+                    _ => None,
+                }).collect();
+                if sort { rules.sort_by_key(|event_line| calendar_position(&event_line.rule)); }
+                out.extend(format_rules(&rules));
+            }
+        }
+        idx = end;
+    }
+    while out.last().is_some_and(|line| line.is_empty()) { out.pop(); }
+
+    let newline = if content.contains("\r\n") { "\r\n" } else { "\n" };
+    Ok(out.iter().map(|line| format!("{}{}", line, newline)).collect())
+}
+
+fn same_kind(a: &Item, b: &Item) -> bool {
+    std::mem::discriminant(a) == std::mem::discriminant(b)
+}
+
+fn format_rules(rules: &[&EventLine]) -> Vec<String> {
+    let width = rules.iter().map(|event_line| event_line.rule_text.chars().count()).max().unwrap_or(0);
+    rules.iter().map(|event_line| {
+        format!("{:<width$} ;{}{}", event_line.rule_text, event_line.meta_block(), event_line.description).trim_end().to_string()
+    }).collect()
+}
+
+// Settings in the order fg, bg, label, priority, count, each one a column as wide as its widest value
+fn format_categories(items: &[Item]) -> Vec<String> {
+    let rows: Vec<Vec<String>> = items.iter().filter_map(|item| match item {
+        Item::Category(name, def) => Some(vec![
+            name.clone(),
+            def.fg_color.as_ref().map(|c| format!("fg={}", c)).unwrap_or_default(),
+            def.bg_color.as_ref().map(|c| format!("bg={}", c)).unwrap_or_default(),
+            def.label.as_ref().map(|l| format!("label=\"{}\"", l)).unwrap_or_default(),
+            def.priority.map(|p| format!("priority={}", p)).unwrap_or_default(),
+            def.count_format.as_ref().map(|c| format!("count=\"{}\"", c)).unwrap_or_default(),
+        ]),
+        _ => None,
+    }).collect();
+    let widths: Vec<usize> = (0..6).map(|col| rows.iter().map(|row| row[col].chars().count()).max().unwrap_or(0)).collect();
+    rows.iter().map(|row| {
+        let columns: Vec<String> = row.iter().zip(&widths).filter(|(_, width)| **width > 0)
+            .map(|(value, width)| format!("{:<width$}", value, width = *width)).collect();
+        format!("@category {}", columns.join(" ")).trim_end().to_string()
+    }).collect()
+}
+
+// Where a rule falls in a year, for sorting: Nth weekdays at their earliest possible day and Easter
+// offsets counted from the earliest Easter, 22 March
+fn calendar_position(rule: &Rule) -> (u32, u32) {
+    match *rule {
+        Rule::Fixed(date) => (date.month(), date.day()),
+        Rule::Annual { month, day } | Rule::InYear { month, day, .. } | Rule::IfWeekday { month, day, .. } => (month, day),
+        Rule::NthWeekday { month, n, .. } => (month, (n - 1) * 7 + 1),
+        Rule::Easter(offset) => {
+            let day_of_year = 81i64.saturating_add(offset).clamp(1, 365) as u32; // 22 March in a common year
+            NaiveDate::from_yo_opt(2001, day_of_year).map_or((12, 31), |date| (date.month(), date.day()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSY: &str = "# Holidays \n\n\n1/1 ;[ie,red]   New Year\n11/4#4   ; Thanksgiving \nE-2;[church] Good Friday\n# Birthdays\n\
+                         01-01-1950 ;[bday , magenta] 🎂 Joe Sand\n@category bday fg=magenta count=\"{nth} birthday\"\n@category  anni  bg=blue\n\n\n";
+
+    #[test]
+    fn shipped_events_file_is_formatted() {
+        let content = include_str!("../events.txt");
+        assert_eq!(format_source("events.txt", content, false).unwrap(), content);
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        for content in [MESSY, include_str!("../events.txt")] {
+            for sort in [false, true] {
+                let once = format_source("events.txt", content, sort).unwrap();
+                assert_eq!(format_source("events.txt", &once, sort).unwrap(), once);
+            }
+        }
+    }
+
+    #[test]
+    fn formatting_keeps_the_meaning() {
+        let events = |content: &str| -> Vec<Line> {
+            parser::parse_source("events.txt", content).0.into_iter().map(|(_, line)| line).filter(|line| *line != Line::Blank).collect()
+        };
+        let formatted = format_source("events.txt", MESSY, false).unwrap();
+        assert_eq!(events(&formatted), events(MESSY));
+        assert!(!formatted.contains("\n\n\n") && !formatted.ends_with("\n\n"), "{}", formatted);
+    }
+}
@@ -0,0 +1,162 @@
+// Backups of the events file. Every write keeps the previous content in .ecal-backups next to the
+// file, named after the file and the time of the change, and only the newest BACKUPS_KEPT stay.
+// Version 0 is the current file, version N the content before the Nth most recent change.
+
+use crate::store;
+use chrono::{Duration, Local, NaiveDateTime};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const BACKUPS_KEPT: usize = 20;
+const BACKUP_DIR: &str = ".ecal-backups";
+const STAMP_FORMAT: &str = "%Y%m%d-%H%M%S%.3f";
+
+// Keeps `content`, the current content of `target`, before the file is replaced
+pub fn backup(target: &Path, content: &str) -> Result<(), String> {
+    let (dir, name) = backup_location(target);
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create backup directory '{}': {}", dir.display(), e))?;
+    let mut stamp = Local::now().naive_local();
+    let mut path = dir.join(format!("{}.{}", name, stamp.format(STAMP_FORMAT)));
+    while path.exists() {
+        stamp += Duration::milliseconds(1);
+        path = dir.join(format!("{}.{}", name, stamp.format(STAMP_FORMAT)));
+    }
+    fs::write(&path, content).map_err(|e| format!("Cannot write backup '{}': {}", path.display(), e))?;
+    for (_, old) in backups(target).into_iter().skip(BACKUPS_KEPT) {
+        let _ = fs::remove_file(old);
+    }
+    Ok(())
+}
+
+pub fn run_history(events_file: &str, version: Option<usize>) -> Result<(), String> {
+    let target = store::resolve(events_file);
+    let versions = backups(&target);
+    if versions.is_empty() {
+        println!("No backups of '{}' yet; they are made whenever ecal changes the file.", events_file);
+        return Ok(());
+    }
+    if let Some(version) = version {
+        let (older, newer) = (read_version(&target, &versions, version)?, read_version(&target, &versions, version - 1)?);
+        let newer_name = if version == 1 { "current".to_string() } else { format!("version {}", version - 1) };
+        println!("\x1b[1mChange {} of '{}' at {}\x1b[0m (version {} -> {})", version, events_file, versions[version - 1].0.format("%Y-%m-%d %H:%M:%S"), version, newer_name);
+        print_diff(&older, &newer);
+        return Ok(());
+    }
+    println!("\x1b[1mChanges to '{}', newest first\x1b[0m ('ecal history N' shows one, 'ecal undo N' restores the version before it):", events_file);
+    for (idx, (stamp, _)) in versions.iter().enumerate() {
+        let (older, newer) = (read_version(&target, &versions, idx + 1)?, read_version(&target, &versions, idx)?);
+        let (added, removed) = diff_counts(&older, &newer);
+        println!("  {:>3}  {}  \x1b[32m+{}\x1b[0m \x1b[31m-{}\x1b[0m", idx + 1, stamp.format("%Y-%m-%d %H:%M:%S"), added, removed);
+    }
+    Ok(())
+}
+
+// Puts back the content from before the Nth most recent change. The restore is a change like any
+// other, so the content it replaces becomes the newest backup and 'ecal undo' again reverts it
+pub fn run_undo(events_file: &str, version: usize) -> Result<(), String> {
+    let target = store::resolve(events_file);
+    let mut summary = (0, 0, NaiveDateTime::default());
+    store::modify(events_file, |content| {
+        let versions = backups(&target);
+        let restored = read_version(&target, &versions, version)?;
+        let (added, removed) = diff_counts(content, &restored);
+        summary = (added, removed, versions[version - 1].0);
+        Ok(restored)
+    })?;
+    println!("Restored '{}' to before the change at {} (\x1b[32m+{}\x1b[0m \x1b[31m-{}\x1b[0m lines); 'ecal undo' reverts this.",
+        events_file, summary.2.format("%Y-%m-%d %H:%M:%S"), summary.0, summary.1);
+    Ok(())
+}
+
+fn backup_location(target: &Path) -> (PathBuf, String) {
+    let dir = target.parent().unwrap_or(Path::new(".")).join(BACKUP_DIR);
+    let name = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    (dir, name)
+}
+
+// Backups of the file with the time they were taken, newest first
+fn backups(target: &Path) -> Vec<(NaiveDateTime, PathBuf)> {
+    let (dir, name) = backup_location(target);
+    let prefix = format!("{}.", name);
+    let mut found: Vec<(NaiveDateTime, PathBuf)> = fs::read_dir(&dir).into_iter().flatten().flatten()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let stamp = NaiveDateTime::parse_from_str(file_name.strip_prefix(&prefix)?, STAMP_FORMAT).ok()?;
+            Some((stamp, entry.path()))
+        })
+        .collect();
+    found.sort_by_key(|(stamp, _)| std::cmp::Reverse(*stamp));
+    found
+}
+
+fn read_version(target: &Path, versions: &[(NaiveDateTime, PathBuf)], version: usize) -> Result<String, String> {
+    let path = match version {
+        0 => target,
+        n if n <= versions.len() => versions[n - 1].1.as_path(),
+        n => return Err(format!("There is no version {}; 'ecal history' lists the {} kept.", n, versions.len())),
+    };
+    fs::read_to_string(path).map_err(|e| format!("Cannot read '{}': {}", path.display(), e))
+}
+
+enum Change<'a> {
+    Same,
+    Removed(usize, &'a str),
+    Added(usize, &'a str),
+}
+
+// Line diff by longest common subsequence, after skipping the common start and end so that the
+// table only covers the changed region
+fn diff<'a>(old: &'a str, new: &'a str) -> Vec<Change<'a>> {
+    let (old, new): (Vec<&str>, Vec<&str>) = (old.lines().collect(), new.lines().collect());
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let (a, b) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+        }
+    }
+
+    let mut changes: Vec<Change> = (0..prefix).map(|_| Change::Same).collect();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            changes.push(Change::Same);
+            i += 1;
+            j += 1;
+        } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            changes.push(Change::Removed(prefix + i + 1, a[i]));
+            i += 1;
+        } else {
+            changes.push(Change::Added(prefix + j + 1, b[j]));
+            j += 1;
+        }
+    }
+    changes.extend((0..suffix).map(|_| Change::Same));
+    changes
+}
+
+fn diff_counts(old: &str, new: &str) -> (usize, usize) {
+    diff(old, new).iter().fold((0, 0), |(added, removed), change| match change {
+        Change::Same => (added, removed),
+        Change::Added(..) => (added + 1, removed),
+        Change::Removed(..) => (added, removed + 1),
+    })
+}
+
+// Changed lines with their line numbers in the older (-) and newer (+) version
+pub fn print_diff(old: &str, new: &str) {
+    let changes = diff(old, new);
+    if changes.iter().all(|c| matches!(c, Change::Same)) {
+        println!("  (no differences)");
+    }
+    for change in changes {
+        match change {
+            Change::Same => {}
+            Change::Removed(line, text) => println!("\x1b[31m{:>5} - {}\x1b[0m", line, text),
+            Change::Added(line, text) => println!("\x1b[32m{:>5} + {}\x1b[0m", line, text),
+        }
+    }
+}
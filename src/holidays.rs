@@ -0,0 +1,77 @@
+// Built-in holiday packs. Each pack is written in the same format as events.txt,
+// so `--print-holidays` output can be pasted into an events file and edited there.
+
+pub struct HolidayPack {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub parent: Option<&'static str>, // Regional packs extend their country pack
+    pub rules: &'static str,
+}
+
+pub const PACKS: &[HolidayPack] = &[
+    HolidayPack { code: "at", name: "Austria", parent: None, rules: include_str!("../holidays/at.txt") },
+    HolidayPack { code: "ca", name: "Canada", parent: None, rules: include_str!("../holidays/ca.txt") },
+    HolidayPack { code: "de", name: "Germany", parent: None, rules: include_str!("../holidays/de.txt") },
+    HolidayPack { code: "de-be", name: "Germany - Berlin", parent: Some("de"), rules: include_str!("../holidays/de-be.txt") },
+    HolidayPack { code: "de-bw", name: "Germany - Baden-Württemberg", parent: Some("de"), rules: include_str!("../holidays/de-bw.txt") },
+    HolidayPack { code: "de-by", name: "Germany - Bayern", parent: Some("de"), rules: include_str!("../holidays/de-by.txt") },
+    HolidayPack { code: "de-he", name: "Germany - Hessen", parent: Some("de"), rules: include_str!("../holidays/de-he.txt") },
+    HolidayPack { code: "de-nw", name: "Germany - Nordrhein-Westfalen", parent: Some("de"), rules: include_str!("../holidays/de-nw.txt") },
+    HolidayPack { code: "de-sn", name: "Germany - Sachsen", parent: Some("de"), rules: include_str!("../holidays/de-sn.txt") },
+    HolidayPack { code: "es", name: "Spain", parent: None, rules: include_str!("../holidays/es.txt") },
+    HolidayPack { code: "fr", name: "France", parent: None, rules: include_str!("../holidays/fr.txt") },
+    HolidayPack { code: "gb", name: "United Kingdom - England and Wales", parent: None, rules: include_str!("../holidays/gb.txt") },
+    HolidayPack { code: "gb-nir", name: "United Kingdom - Northern Ireland", parent: Some("gb"), rules: include_str!("../holidays/gb-nir.txt") },
+    HolidayPack { code: "gb-sct", name: "United Kingdom - Scotland", parent: None, rules: include_str!("../holidays/gb-sct.txt") },
+    HolidayPack { code: "hr", name: "Croatia", parent: None, rules: include_str!("../holidays/hr.txt") },
+    HolidayPack { code: "ie", name: "Ireland", parent: None, rules: include_str!("../holidays/ie.txt") },
+    HolidayPack { code: "it", name: "Italy", parent: None, rules: include_str!("../holidays/it.txt") },
+    HolidayPack { code: "nl", name: "Netherlands", parent: None, rules: include_str!("../holidays/nl.txt") },
+    HolidayPack { code: "pl", name: "Poland", parent: None, rules: include_str!("../holidays/pl.txt") },
+    HolidayPack { code: "us", name: "United States", parent: None, rules: include_str!("../holidays/us.txt") },
+];
+
+pub fn find_pack(code: &str) -> Option<&'static HolidayPack> {
+    PACKS.iter().find(|p| p.code.eq_ignore_ascii_case(code))
+}
+
+// Returns a pack together with the pack it extends, parent pack first
+pub fn collect_packs(code: &str) -> Option<Vec<&'static HolidayPack>> {
+    let pack = find_pack(code)?;
+    let mut packs = Vec::new();
+    if let Some(parent) = pack.parent.and_then(find_pack) {
+        packs.push(parent);
+    }
+    packs.push(pack);
+    Some(packs)
+}
+
+// The packs of all selected codes with their parents, each pack once and in the order first
+// selected, so e.g. "de,de-by" reads the German pack only once
+pub fn selected_packs(codes: &[String]) -> Vec<&'static HolidayPack> {
+    let mut selected: Vec<&'static HolidayPack> = Vec::new();
+    for code in codes {
+        match collect_packs(code) {
+            Some(packs) => {
+                for pack in packs {
+                    if !selected.iter().any(|p| p.code == pack.code) { selected.push(pack); }
+                }
+            }
+            None => eprintln!("Warning: Unknown holiday pack '{}'. Use --holidays list to see available packs.", code),
+        }
+    }
+    selected
+}
+
+// Name used for the pack in diagnostics
+pub fn source_name(pack: &HolidayPack) -> String {
+    format!("holidays/{}.txt", pack.code)
+}
+
+pub fn print_pack_list() {
+    println!("\n\x1b[1mAvailable holiday packs:\x1b[0m");
+    for pack in PACKS {
+        let includes = pack.parent.map(|p| format!(" (includes {})", p)).unwrap_or_default();
+        println!(" \x1b[32m{:<7}\x1b[0m {}{}", pack.code, pack.name, includes);
+    }
+}
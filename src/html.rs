@@ -0,0 +1,130 @@
+// HTML rendering of the calendar grid and events list as a single self-contained page,
+// styled like the terminal output: week numbers, red weekends, today and event colors.
+
+use crate::render::{full_description, months_per_row, weekday_abbreviations};
+use crate::{Config, Event, css_color, displayed_months, events_in_display_range, events_on, month_name, month_weeks};
+use chrono::{Datelike, NaiveDate, Weekday};
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; color: #222; }
+.months { display: flex; flex-wrap: wrap; gap: 2em; }
+table.month { border-collapse: collapse; font-family: monospace; font-size: 1.1em; }
+table.month caption { font-weight: bold; padding-bottom: 0.3em; }
+table.month th, table.month td { width: 2em; height: 1.8em; text-align: right; padding: 0 0.3em; }
+.wk { color: #2472c8; }
+.weekend { color: #cd3131; }
+.event { font-weight: bold; cursor: help; }
+.event.inverse { background: #222; color: #fff; }
+.today { background: #e5e510; color: #000; font-weight: bold; }
+ul.events { list-style: none; padding: 0; font-family: monospace; }
+ul.events li { padding: 0.15em 0; }
+ul.events time { padding: 0 0.2em; }
+";
+
+pub fn render_html(config: &Config, events: &[Event]) -> String {
+    let today = chrono::Local::now().naive_local().date();
+    let (filtered_events, start_date, end_date) = events_in_display_range(config, events);
+    let last_date = end_date.pred_opt().unwrap_or(end_date);
+
+    let mut out = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>Calendar {} – {}</title>\n", start_date.format("%b %Y"), last_date.format("%b %Y")));
+    out.push_str(&format!("<style>\n{}</style>\n</head>\n<body>\n", STYLE));
+
+    if config.show_calendar {
+        out.push_str(&format!("<div class=\"months\" style=\"max-width: {}em\">\n", months_per_row(config) * 22));
+        for month_start in displayed_months(config) {
+            render_month(&mut out, month_start, config, events, today);
+        }
+        out.push_str("</div>\n");
+    }
+
+    if config.show_events && !filtered_events.is_empty() {
+        out.push_str("<h2>Events</h2>\n<ul class=\"events\">\n");
+        for event in filtered_events {
+            let label = event.label.as_ref().map(|l| format!("[{}] ", l)).unwrap_or_default();
+            let today_mark = if event.date == today { " <strong>(Today)</strong>" } else { "" };
+            out.push_str(&format!(
+                "<li><time datetime=\"{}\"{}>{}</time> – {}{}{}</li>\n",
+                event.date.format("%Y-%m-%d"),
+                color_style(event.fg_color.as_deref(), event.bg_color.as_deref()),
+                event.date.format("%a, %d %b %Y"),
+                escape(&label),
+                escape(&full_description(event)),
+                today_mark,
+            ));
+        }
+        out.push_str("</ul>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn render_month(out: &mut String, month_start: NaiveDate, config: &Config, events: &[Event], today: NaiveDate) {
+    out.push_str("<table class=\"month\">\n");
+    out.push_str(&format!("<caption>{} {}</caption>\n<thead><tr>", month_name(month_start.month()), month_start.year()));
+    if config.show_week_numbers { out.push_str("<th class=\"wk\">Wk</th>"); }
+    for name in weekday_abbreviations(config.monday_first) {
+        let class = if name == "Sa" || name == "Su" { " class=\"weekend\"" } else { "" };
+        out.push_str(&format!("<th{}>{}</th>", class, name));
+    }
+    out.push_str("</tr></thead>\n<tbody>\n");
+
+    for (week_number, days) in month_weeks(month_start, config.monday_first) {
+        out.push_str("<tr>");
+        if config.show_week_numbers { out.push_str(&format!("<td class=\"wk\">{}</td>", week_number)); }
+        for day in days {
+            match day {
+                Some(date) => out.push_str(&day_cell(date, events_on(events, date), today)),
+                None => out.push_str("<td></td>"),
+            }
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</tbody>\n</table>\n");
+}
+
+// Mirrors print_week_row: today wins over weekend styling, which wins over event colors
+fn day_cell(date: NaiveDate, day_events: &[Event], today: NaiveDate) -> String {
+    let is_weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+    let first_event = day_events.first();
+    let mut classes = vec!["day"];
+    let mut style = String::new();
+
+    if first_event.is_some() { classes.push("event"); }
+    if date == today {
+        classes.push("today");
+        if let Some(bg) = first_event.and_then(|e| e.bg_color.as_deref()).and_then(css_color) {
+            style = format!(" style=\"background: {}\"", bg);
+        }
+    } else if is_weekend {
+        classes.push("weekend");
+    } else if let Some(event) = first_event {
+        let fg = event.fg_color.as_deref().and_then(css_color);
+        if event.bg_color.as_deref().and_then(css_color).is_some() {
+            style = color_style(event.fg_color.as_deref(), event.bg_color.as_deref());
+        } else {
+            // Like the terminal's inverse video: the event color becomes the background
+            classes.push("inverse");
+            if let Some(fg) = fg { style = format!(" style=\"background: {}\"", fg); }
+        }
+    }
+
+    let title = if day_events.is_empty() {
+        String::new()
+    } else {
+        let lines: Vec<String> = day_events.iter().map(full_description).collect();
+        format!(" title=\"{}\"", escape(&lines.join("\n")))
+    };
+    format!("<td class=\"{}\"{}{}>{}</td>", classes.join(" "), style, title, date.day())
+}
+
+fn color_style(fg_color: Option<&str>, bg_color: Option<&str>) -> String {
+    let mut declarations = Vec::new();
+    if let Some(fg) = fg_color.and_then(css_color) { declarations.push(format!("color: {}", fg)); }
+    if let Some(bg) = bg_color.and_then(css_color) { declarations.push(format!("background: {}", bg)); }
+    if declarations.is_empty() { String::new() } else { format!(" style=\"{}\"", declarations.join("; ")) }
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,561 @@
+// iCalendar (RFC 5545) export and import. On export, rules that repeat every year on a fixed
+// pattern become a single VEVENT with an RRULE; everything else is written as expanded instances.
+// On import, simple RRULEs are translated back into rules and the rest into fixed dates.
+
+use crate::{Event, ImportReport};
+use crate::parser::{EventLine, Rule, normalize_category, parse_rule};
+use chrono::{Datelike, NaiveDate, Weekday};
+use std::collections::HashMap;
+
+// Yearly rules become one recurring VEVENT without an end, as the rule has none; other rules are
+// written as their dates in the export
+pub fn export_ics(events: &[Event]) -> String {
+    let mut out = String::new();
+    push_line(&mut out, "BEGIN:VCALENDAR");
+    push_line(&mut out, "VERSION:2.0");
+    push_line(&mut out, &format!("PRODID:-//ecal//ecal {}//EN", env!("CARGO_PKG_VERSION")));
+    push_line(&mut out, "CALSCALE:GREGORIAN");
+
+    let dtstamp = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+    // Group the expanded instances by the rule line they came from, ordered by first occurrence
+    let mut groups: Vec<Vec<&Event>> = Vec::new();
+    let mut group_index: HashMap<(&str, usize), usize> = HashMap::new();
+    for event in events {
+        let idx = *group_index.entry((event.source.as_str(), event.line)).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[idx].push(event);
+    }
+    groups.sort_by_key(|g| g[0].date);
+
+    for group in groups {
+        let first = group[0];
+        let rule_uid = format!("{:016x}", crate::fnv1a_hash(&format!("{}|{}|{}", first.rule_text, first.category.as_deref().unwrap_or(""), first.description)));
+        match yearly_rrule(first) {
+            Some(rrule) => {
+                // Counting categories start at the original date so calendars can show the years
+                let start = match first.rule {
+                    Rule::Fixed(date) if first.original_year.is_some() => date,
+                    _ => first.date,
+                };
+                push_vevent(&mut out, first, start, &format!("{}@ecal", rule_uid), &dtstamp, Some(&rrule));
+            }
+            None => {
+                for event in group {
+                    let uid = format!("{}-{}@ecal", rule_uid, event.date.format("%Y%m%d"));
+                    push_vevent(&mut out, event, event.date, &uid, &dtstamp, None);
+                }
+            }
+        }
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+fn push_vevent(out: &mut String, event: &Event, start: NaiveDate, uid: &str, dtstamp: &str, rrule: Option<&str>) {
+    let end = start.succ_opt().unwrap_or(start);
+    push_line(out, "BEGIN:VEVENT");
+    push_line(out, &format!("UID:{}", uid));
+    push_line(out, &format!("DTSTAMP:{}", dtstamp));
+    push_line(out, &format!("DTSTART;VALUE=DATE:{}", start.format("%Y%m%d")));
+    push_line(out, &format!("DTEND;VALUE=DATE:{}", end.format("%Y%m%d")));
+    if let Some(rrule) = rrule {
+        push_line(out, &format!("RRULE:{}", rrule));
+    }
+    push_line(out, &format!("SUMMARY:{}", escape_text(&event.description)));
+    if let Some(category) = &event.category {
+        push_line(out, &format!("CATEGORIES:{}", escape_text(category)));
+    }
+    push_line(out, "TRANSP:TRANSPARENT");
+    push_line(out, "END:VEVENT");
+}
+
+// RRULE for rules that fall on the same pattern every year, None for rules that need expanding
+fn yearly_rrule(event: &Event) -> Option<String> {
+    match event.rule {
+        Rule::Annual { month, day } => Some(format!("FREQ=YEARLY;BYMONTH={};BYMONTHDAY={}", month, day)),
+        Rule::NthWeekday { month, weekday, n } => {
+            // Occurrence 5 means "the last one" in our rules
+            let nth = if n == 5 { -1 } else { n as i32 };
+            Some(format!("FREQ=YEARLY;BYMONTH={};BYDAY={}{}", month, nth, weekday_code(weekday)))
+        }
+        Rule::Fixed(date) if event.original_year.is_some() => {
+            Some(format!("FREQ=YEARLY;BYMONTH={};BYMONTHDAY={}", date.month(), date.day()))
+        }
+        _ => None,
+    }
+}
+
+pub fn weekday_code(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO", Weekday::Tue => "TU", Weekday::Wed => "WE", Weekday::Thu => "TH",
+        Weekday::Fri => "FR", Weekday::Sat => "SA", Weekday::Sun => "SU",
+    }
+}
+
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(';', "\\;").replace(',', "\\,").replace('\n', "\\n")
+}
+
+// Content lines are folded at 75 octets with CRLF followed by a space (RFC 5545, 3.1)
+fn push_line(out: &mut String, line: &str) {
+    let mut line_len = 0;
+    for c in line.chars() {
+        if line_len + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            line_len = 1;
+        }
+        out.push(c);
+        line_len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+// --- Import ---
+
+#[derive(Debug, Default)]
+struct IcsEvent {
+    summary: String,
+    category: Option<String>,
+    dtstart: Option<NaiveDate>,
+    rrule: Option<String>,
+    exdates: Vec<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Freq { Daily, Weekly, Monthly, Yearly }
+
+#[derive(Debug)]
+struct Recurrence {
+    freq: Freq,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<NaiveDate>,
+    by_day: Vec<(i32, Weekday)>, // (0, day) means every such weekday
+    by_month_day: Vec<i32>,
+    by_month: Vec<u32>,
+}
+
+// Recurrences expanding to more dates than this are refused rather than flooding the events file
+const MAX_EXPANDED_DATES: usize = 1000;
+
+// Translates the VEVENTs of an .ics file into events file lines. Recurrences without a native
+// equivalent are expanded to the fixed dates that fall in `window` (and within their own COUNT/UNTIL).
+pub fn import_ics(content: &str, window: (NaiveDate, NaiveDate)) -> ImportReport {
+    let (window_start, window_end) = window;
+    let mut report = ImportReport::default();
+    for event in parse_vevents(content) {
+        let Some(start) = event.dtstart else {
+            report.skipped.push(format!("\"{}\" has no DTSTART", event.summary));
+            continue;
+        };
+        let make_line = |rule_text: String| {
+            EventLine { rule: Rule::Fixed(start), rule_text, category: event.category.clone(), fg_color: None, bg_color: None, description: event.summary.clone() }.to_line()
+        };
+        let fixed_date = |date: NaiveDate| date.format("%d-%m-%Y").to_string();
+
+        let Some(rrule) = &event.rrule else {
+            report.lines.push(make_line(fixed_date(start)));
+            report.one_off += 1;
+            continue;
+        };
+
+        let recurrence = match parse_rrule(rrule) {
+            Ok(recurrence) => recurrence,
+            Err(reason) => {
+                report.lines.push(make_line(fixed_date(start)));
+                report.as_fixed_dates.push(format!("\"{}\" ({}; imported the first occurrence only)", event.summary, reason));
+                continue;
+            }
+        };
+
+        // Yearly birthdays and anniversaries keep their original year so the years get counted (an
+        // UNTIL doesn't end a birthday)
+        let is_plain_yearly = recurrence.freq == Freq::Yearly && recurrence.interval == 1 && recurrence.count.is_none()
+            && recurrence.by_day.is_empty()
+            && recurrence.by_month.iter().all(|m| *m == start.month()) && recurrence.by_month_day.iter().all(|d| *d == start.day() as i32);
+        if is_plain_yearly && event.exdates.is_empty() && matches!(event.category.as_deref(), Some("bday" | "anni")) {
+            report.lines.push(make_line(fixed_date(start)));
+            report.recurring += 1;
+            continue;
+        }
+
+        // Rules repeat every year, so they only do when they have no date between the window start
+        // and DTSTART
+        if event.exdates.is_empty() && let Some(rules) = native_rules(start, &recurrence) && !occurs_between(&rules, window_start, start) {
+            report.lines.extend(rules.into_iter().map(make_line));
+            report.recurring += 1;
+            continue;
+        }
+
+        if start > window_end {
+            report.lines.push(make_line(fixed_date(start)));
+            report.as_fixed_dates.push(format!("\"{}\" (starts after {}; imported the first occurrence only)", event.summary, window_end));
+            continue;
+        }
+
+        let Some(dates) = expand_recurrence(start, &recurrence, window_start, window_end) else {
+            report.skipped.push(format!("\"{}\" (more than {} occurrences between {} and {}; narrow it with --from/--to)", event.summary, MAX_EXPANDED_DATES, window_start, window_end));
+            continue;
+        };
+        let dates: Vec<NaiveDate> = dates.into_iter().filter(|d| !event.exdates.contains(d)).collect();
+        let Some(end) = dates.last().copied() else {
+            report.skipped.push(format!("\"{}\" (no occurrences between {} and {})", event.summary, window_start, window_end));
+            continue;
+        };
+        report.lines.extend(dates.iter().map(|d| make_line(fixed_date(*d))));
+        report.as_fixed_dates.push(format!("\"{}\" ({} occurrence(s) until {})", event.summary, dates.len(), end));
+    }
+    report
+}
+
+fn parse_vevents(content: &str) -> Vec<IcsEvent> {
+    // Unfold continuation lines first (RFC 5545, 3.1)
+    let mut lines: Vec<String> = Vec::new();
+    for raw in content.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut current: Option<IcsEvent> = None;
+    let mut nesting = 0; // VALARMs and other components inside a VEVENT
+    for line in &lines {
+        let Some((name, value)) = split_property(line) else { continue };
+        match (name.as_str(), value) {
+            ("BEGIN", "VEVENT") => { current = Some(IcsEvent::default()); nesting = 0; }
+            ("END", "VEVENT") => events.extend(current.take()),
+            ("BEGIN", _) if current.is_some() => nesting += 1,
+            ("END", _) if current.is_some() => nesting -= 1,
+            _ => {}
+        }
+        let Some(event) = current.as_mut().filter(|_| nesting == 0) else { continue };
+        match name.as_str() {
+            "SUMMARY" => event.summary = unescape_text(value),
+            "CATEGORIES" => event.category = normalize_category(&unescape_text(value.split(',').next().unwrap_or(""))),
+            "DTSTART" => event.dtstart = parse_ics_date(value),
+            "RRULE" => event.rrule = Some(value.to_string()),
+            "EXDATE" => event.exdates.extend(value.split(',').filter_map(parse_ics_date)),
+            _ => {}
+        }
+    }
+    events
+}
+
+// Returns the upper-cased property name (without parameters) and the value
+fn split_property(line: &str) -> Option<(String, &str)> {
+    let mut in_quotes = false;
+    for (pos, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ':' if !in_quotes => {
+                let name = line[..pos].split(';').next().unwrap_or("").trim().to_uppercase();
+                return Some((name, &line[pos + 1..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Accepts DATE and DATE-TIME values; the time part (and time zone) is dropped
+fn parse_ics_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim().get(..8)?, "%Y%m%d").ok()
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut out = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' { out.push(c); continue; }
+        match chars.next() {
+            Some('n') | Some('N') => out.push(' '),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out.trim().to_string()
+}
+
+fn parse_rrule(rrule: &str) -> Result<Recurrence, String> {
+    let mut recurrence = Recurrence { freq: Freq::Yearly, interval: 1, count: None, until: None, by_day: Vec::new(), by_month_day: Vec::new(), by_month: Vec::new() };
+    let mut freq = None;
+    for part in rrule.split(';').filter(|p| !p.is_empty()) {
+        let (key, value) = part.split_once('=').ok_or_else(|| format!("malformed RRULE part '{}'", part))?;
+        let invalid = || format!("unsupported RRULE value {}={}", key, value);
+        match key.to_uppercase().as_str() {
+            "FREQ" => freq = Some(match value.to_uppercase().as_str() {
+                "DAILY" => Freq::Daily, "WEEKLY" => Freq::Weekly, "MONTHLY" => Freq::Monthly, "YEARLY" => Freq::Yearly,
+                _ => return Err(format!("unsupported frequency {}", value)),
+            }),
+            "INTERVAL" => recurrence.interval = value.parse().ok().filter(|i| *i > 0).ok_or_else(invalid)?,
+            "COUNT" => recurrence.count = Some(value.parse().map_err(|_| invalid())?),
+            "UNTIL" => recurrence.until = Some(parse_ics_date(value).ok_or_else(invalid)?),
+            "BYDAY" => {
+                for day in value.split(',') {
+                    let split = day.len().saturating_sub(2);
+                    let (nth, code) = (day.get(..split).unwrap_or(""), day.get(split..).unwrap_or(""));
+                    let weekday = parse_weekday_code(code).ok_or_else(invalid)?;
+                    let nth = if nth.is_empty() { 0 } else { nth.parse::<i32>().ok().filter(|n| (-5..=5).contains(n) && *n != 0).ok_or_else(invalid)? };
+                    recurrence.by_day.push((nth, weekday));
+                }
+            }
+            "BYMONTHDAY" => {
+                for day in value.split(',') {
+                    recurrence.by_month_day.push(day.parse::<i32>().ok().filter(|d| (1..=31).contains(&d.abs())).ok_or_else(invalid)?);
+                }
+            }
+            "BYMONTH" => {
+                for month in value.split(',') {
+                    recurrence.by_month.push(month.parse::<u32>().ok().filter(|m| (1..=12).contains(m)).ok_or_else(invalid)?);
+                }
+            }
+            "WKST" => {}
+            other => return Err(format!("unsupported RRULE part {}", other)),
+        }
+    }
+    recurrence.freq = freq.ok_or("RRULE without FREQ")?;
+    if recurrence.freq == Freq::Yearly && recurrence.by_month.is_empty() && recurrence.by_day.iter().any(|(n, _)| *n != 0) {
+        return Err("yearly BYDAY without BYMONTH".to_string());
+    }
+    Ok(recurrence)
+}
+
+fn parse_weekday_code(code: &str) -> Option<Weekday> {
+    [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun]
+        .into_iter().find(|w| weekday_code(*w).eq_ignore_ascii_case(code))
+}
+
+// Native rules for open-ended yearly and monthly recurrences (a monthly event becomes one rule per month)
+fn native_rules(start: NaiveDate, recurrence: &Recurrence) -> Option<Vec<String>> {
+    if recurrence.interval != 1 || recurrence.count.is_some() || recurrence.until.is_some() {
+        return None;
+    }
+    let months: Vec<u32> = match recurrence.freq {
+        Freq::Yearly if recurrence.by_month.is_empty() => vec![start.month()],
+        Freq::Yearly => recurrence.by_month.clone(),
+        Freq::Monthly if recurrence.by_month.is_empty() => (1..=12).collect(),
+        Freq::Monthly => recurrence.by_month.clone(),
+        Freq::Daily | Freq::Weekly => return None,
+    };
+
+    let mut rules = Vec::new();
+    for month in months {
+        if !recurrence.by_day.is_empty() {
+            if !recurrence.by_month_day.is_empty() { return None; }
+            for (nth, weekday) in &recurrence.by_day {
+                // Our #5 means "the last one", so -1 maps onto it; other counts from the end don't
+                let n = match nth { 1..=4 => *nth, -1 => 5, _ => return None };
+                rules.push(format!("{}/{}#{}", month, weekday.num_days_from_sunday(), n));
+            }
+        } else if recurrence.by_month_day.is_empty() {
+            rules.push(format!("{}/{}", month, start.day()));
+        } else {
+            for day in &recurrence.by_month_day {
+                if *day < 0 { return None; }
+                rules.push(format!("{}/{}", month, day));
+            }
+        }
+    }
+    // Drop days that never exist in a month (e.g. 4/31 from a monthly rule on the 31st)
+    rules.retain(|r| parse_rule(r).is_ok());
+    Some(rules)
+}
+
+// Whether any of the rules has a date on or after `from` and before `until`
+fn occurs_between(rules: &[String], from: NaiveDate, until: NaiveDate) -> bool {
+    let rules: Vec<Rule> = rules.iter().filter_map(|r| parse_rule(r).ok()).collect();
+    (from.year()..=until.year()).any(|year| rules.iter().filter_map(|r| r.date_in_year(year)).any(|d| d >= from && d < until))
+}
+
+// The occurrences between `window_start` and `window_end`, or None when there are more than
+// MAX_EXPANDED_DATES. COUNT counts from DTSTART, so only then is the walk started there.
+fn expand_recurrence(start: NaiveDate, recurrence: &Recurrence, window_start: NaiveDate, window_end: NaiveDate) -> Option<Vec<NaiveDate>> {
+    let end = recurrence.until.map_or(window_end, |until| until.min(window_end));
+    let mut date = if recurrence.count.is_some() { start } else { start.max(window_start) };
+    let mut seen = 0;
+    let mut dates = Vec::new();
+    while date <= end && recurrence.count.is_none_or(|count| seen < count) {
+        if occurs_on(recurrence, start, date) {
+            seen += 1;
+            if date >= window_start {
+                if dates.len() == MAX_EXPANDED_DATES { return None; }
+                dates.push(date);
+            }
+        }
+        let Some(next) = date.succ_opt() else { break };
+        date = next;
+    }
+    Some(dates)
+}
+
+fn occurs_on(recurrence: &Recurrence, start: NaiveDate, date: NaiveDate) -> bool {
+    let interval = recurrence.interval as i64;
+    if !recurrence.by_month.is_empty() && !recurrence.by_month.contains(&date.month()) { return false; }
+    let months_since_start = (date.year() - start.year()) as i64 * 12 + date.month() as i64 - start.month() as i64;
+    match recurrence.freq {
+        Freq::Daily => (date - start).num_days() % interval == 0 && matches_day(recurrence, date, None),
+        Freq::Weekly => {
+            let week_start = |d: NaiveDate| d - chrono::Duration::days(d.weekday().num_days_from_monday() as i64);
+            let weeks = (week_start(date) - week_start(start)).num_days() / 7;
+            let weekday_matches = if recurrence.by_day.is_empty() { date.weekday() == start.weekday() } else { recurrence.by_day.iter().any(|(_, w)| *w == date.weekday()) };
+            weeks % interval == 0 && weekday_matches
+        }
+        Freq::Monthly => months_since_start % interval == 0 && matches_day(recurrence, date, Some(start.day())),
+        Freq::Yearly => {
+            let month_matches = !recurrence.by_month.is_empty() || date.month() == start.month();
+            (date.year() - start.year()) as i64 % interval == 0 && month_matches && matches_day(recurrence, date, Some(start.day()))
+        }
+    }
+}
+
+fn matches_day(recurrence: &Recurrence, date: NaiveDate, default_day: Option<u32>) -> bool {
+    let days_in_month = crate::days_in_month(date.year(), date.month()) as i32;
+    let day = date.day() as i32;
+    if !recurrence.by_month_day.is_empty() {
+        return recurrence.by_month_day.iter().any(|d| if *d > 0 { *d == day } else { days_in_month + d + 1 == day });
+    }
+    if !recurrence.by_day.is_empty() {
+        let nth_from_start = (day - 1) / 7 + 1;
+        let nth_from_end = -((days_in_month - day) / 7 + 1);
+        return recurrence.by_day.iter().any(|(n, w)| *w == date.weekday() && (*n == 0 || *n == nth_from_start || *n == nth_from_end));
+    }
+    default_day.is_none_or(|d| d as i32 == day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Line, parse_line};
+
+    fn event(rule_text: &str, date: &str, line: usize) -> Event {
+        Event {
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            description: format!("Event {}", line),
+            category: None,
+            fg_color: None,
+            bg_color: None,
+            label: None,
+            priority: 0,
+            original_year: None,
+            count_format: None,
+            rule: parse_rule(rule_text).unwrap(),
+            rule_text: rule_text.to_string(),
+            source: "events.txt".to_string(),
+            line,
+        }
+    }
+
+    // (date, description) of every occurrence of the lines in `year`
+    fn occurrences(lines: &[String], year: i32) -> Vec<(NaiveDate, String)> {
+        let mut dates: Vec<(NaiveDate, String)> = lines.iter().filter_map(|text| match parse_line(text) {
+            (Line::Event(event_line), _) => event_line.rule.date_in_year(year).map(|d| (d, event_line.description)),
+            _ => None,
+        }).collect();
+        dates.sort();
+        dates
+    }
+
+    #[test]
+    fn rrules_repeat_every_year() {
+        let ics = export_ics(&[event("7/4", "2026-07-04", 1), event("11/4#4", "2026-11-26", 2)]);
+        let rrules: Vec<&str> = ics.lines().filter(|l| l.starts_with("RRULE:")).collect();
+        assert_eq!(rrules, vec!["RRULE:FREQ=YEARLY;BYMONTH=7;BYMONTHDAY=4", "RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=4TH"]);
+    }
+
+    #[test]
+    fn export_then_import_gives_back_the_rules() {
+        let mut birthday = event("12-11-1990", "2026-11-12", 5);
+        birthday.category = Some("bday".to_string());
+        birthday.original_year = Some(1990);
+        birthday.count_format = Some("{nth} Birthday".to_string());
+        let events = [
+            event("7/4", "2026-07-04", 1),
+            event("11/4#4", "2026-11-26", 2),
+            event("E-2", "2026-04-03", 3),
+            event("10/1#5", "2026-10-26", 4),
+            birthday,
+        ];
+        let report = import_ics(&export_ics(&events), (ymd(2026, 1, 1), ymd(2026, 12, 31)));
+        assert!(report.skipped.is_empty(), "{:?}", report.skipped);
+        let rule_texts: Vec<String> = report.lines.iter().filter_map(|text| match parse_line(text) {
+            (Line::Event(event_line), _) => Some(event_line.rule_text),
+            _ => None,
+        }).collect();
+        // Easter offsets have no RRULE, so their dates come back
+        assert_eq!(rule_texts, vec!["03-04-2026", "7/4", "10/1#5", "12-11-1990", "11/4#4"]);
+        assert_eq!(report.recurring, 4);
+        assert_eq!(occurrences(&report.lines, 2027).len(), 3);
+    }
+
+    fn vevent(dtstart: &str, rrule: &str) -> String {
+        format!("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Test\r\nDTSTART;VALUE=DATE:{}\r\nRRULE:{}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n", dtstart, rrule)
+    }
+
+    fn ymd(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn yearly_recurrence_does_not_occur_before_dtstart() {
+        let report = import_ics(&vevent("20300704", "FREQ=YEARLY"), (ymd(2026, 1, 1), ymd(2031, 12, 31)));
+        for year in 2026..=2029 {
+            assert!(occurrences(&report.lines, year).is_empty(), "{} in {:?}", year, report.lines);
+        }
+        assert_eq!(occurrences(&report.lines, 2030), vec![(ymd(2030, 7, 4), "Test".to_string())]);
+        assert_eq!(occurrences(&report.lines, 2031), vec![(ymd(2031, 7, 4), "Test".to_string())]);
+
+        // Beyond the window only the first occurrence comes in
+        let report = import_ics(&vevent("20300704", "FREQ=YEARLY"), (ymd(2026, 1, 1), ymd(2027, 12, 31)));
+        assert_eq!(report.lines.len(), 1);
+        assert_eq!(occurrences(&report.lines, 2030), vec![(ymd(2030, 7, 4), "Test".to_string())]);
+
+        // Started before the window, so a rule is fine
+        let report = import_ics(&vevent("20200704", "FREQ=YEARLY"), (ymd(2026, 1, 1), ymd(2027, 12, 31)));
+        assert_eq!(report.recurring, 1);
+        assert_eq!(report.lines.len(), 1);
+    }
+
+    #[test]
+    fn recurrence_starting_at_its_first_date_in_the_window_is_a_rule() {
+        let report = import_ics(&vevent("20261225", "FREQ=YEARLY"), (ymd(2026, 10, 18), ymd(2027, 12, 31)));
+        assert_eq!(report.recurring, 1);
+        assert_eq!(occurrences(&report.lines, 2030), vec![(ymd(2030, 12, 25), "Test".to_string())]);
+    }
+
+    #[test]
+    fn expansion_starts_at_the_window() {
+        let report = import_ics(&vevent("20000101", "FREQ=DAILY"), (ymd(2026, 10, 1), ymd(2026, 10, 31)));
+        let dates = occurrences(&report.lines, 2026);
+        assert_eq!(dates.len(), 31);
+        assert_eq!(dates[0].0, ymd(2026, 10, 1));
+    }
+
+    #[test]
+    fn expansion_is_capped() {
+        let report = import_ics(&vevent("20000101", "FREQ=DAILY"), (ymd(2026, 1, 1), ymd(2030, 12, 31)));
+        assert!(report.lines.is_empty());
+        assert_eq!(report.skipped.len(), 1);
+    }
+
+    #[test]
+    fn count_and_until_are_honoured() {
+        // COUNT counts from DTSTART, so all ten are long gone
+        let report = import_ics(&vevent("20000101", "FREQ=DAILY;COUNT=10"), (ymd(2026, 1, 1), ymd(2026, 12, 31)));
+        assert!(report.lines.is_empty());
+
+        let report = import_ics(&vevent("20260101", "FREQ=WEEKLY;COUNT=3"), (ymd(2026, 1, 1), ymd(2026, 12, 31)));
+        let dates: Vec<NaiveDate> = occurrences(&report.lines, 2026).into_iter().map(|(d, _)| d).collect();
+        assert_eq!(dates, vec![ymd(2026, 1, 1), ymd(2026, 1, 8), ymd(2026, 1, 15)]);
+
+        let report = import_ics(&vevent("20250101", "FREQ=MONTHLY;UNTIL=20260315"), (ymd(2026, 1, 1), ymd(2026, 12, 31)));
+        let dates: Vec<NaiveDate> = occurrences(&report.lines, 2026).into_iter().map(|(d, _)| d).collect();
+        assert_eq!(dates, vec![ymd(2026, 1, 1), ymd(2026, 2, 1), ymd(2026, 3, 1)]);
+    }
+}
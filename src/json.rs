@@ -0,0 +1,55 @@
+// JSON and JSON Lines output of expanded events, for scripts that would otherwise scrape
+// the colored terminal output.
+
+use crate::{Event, anniversary_number, format_anniversary_count};
+use chrono::NaiveDate;
+
+pub fn events_to_json(events: &[&Event], from: NaiveDate, to: NaiveDate, json_lines: bool) -> String {
+    if json_lines {
+        return events.iter().map(|e| event_to_json(e) + "\n").collect();
+    }
+    let items: Vec<String> = events.iter().map(|e| format!("    {}", event_to_json(e))).collect();
+    let list = if items.is_empty() { "[]".to_string() } else { format!("[\n{}\n  ]", items.join(",\n")) };
+    format!("{{\n  \"from\": \"{}\",\n  \"to\": \"{}\",\n  \"events\": {}\n}}\n", from, to, list)
+}
+
+fn event_to_json(event: &Event) -> String {
+    let fields = [
+        ("date", string(&event.date.format("%Y-%m-%d").to_string())),
+        ("description", string(&event.description)),
+        ("category", optional_string(event.category.as_deref())),
+        ("label", optional_string(event.label.as_deref())),
+        ("fg_color", optional_string(event.fg_color.as_deref())),
+        ("bg_color", optional_string(event.bg_color.as_deref())),
+        ("priority", event.priority.to_string()),
+        ("original_year", event.original_year.map_or("null".to_string(), |y| y.to_string())),
+        ("anniversary", anniversary_number(event).map_or("null".to_string(), |n| n.to_string())),
+        ("anniversary_text", optional_string(format_anniversary_count(event).as_deref())),
+        ("rule", string(&event.rule_text)),
+        ("source", string(&event.source)),
+        ("line", event.line.to_string()),
+    ];
+    let body: Vec<String> = fields.iter().map(|(key, value)| format!("\"{}\": {}", key, value)).collect();
+    format!("{{{}}}", body.join(", "))
+}
+
+fn optional_string(value: Option<&str>) -> String {
+    value.map_or("null".to_string(), string)
+}
+
+fn string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
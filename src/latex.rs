@@ -0,0 +1,103 @@
+// LaTeX output: one month per page with large day cells listing the day's events, for printing
+// calendars with room for notes. Compiles with pdflatex using only standard packages.
+
+use crate::parser::{COLOR_NAMES, is_known_color};
+use crate::render::{full_description, weekday_names};
+use crate::{Config, Event, css_color, displayed_months, events_on, month_name, month_weeks};
+use chrono::{Datelike, NaiveDate, Weekday};
+
+pub fn render_latex(config: &Config, events: &[Event]) -> String {
+    let mut out = String::from(
+        "\\documentclass[a4paper,landscape]{article}\n\
+         \\usepackage[utf8]{inputenc}\n\
+         \\usepackage[T1]{fontenc}\n\
+         \\usepackage[margin=1.2cm]{geometry}\n\
+         \\usepackage[table]{xcolor}\n\
+         \\usepackage{array,tabularx}\n\
+         \\pagestyle{empty}\n\
+         \\setlength{\\parindent}{0pt}\n\
+         \\newlength{\\dayheight}\n\
+         \\newcommand{\\daycell}[2]{\\parbox[t][\\dayheight][t]{\\linewidth}{\\raggedright #1\\par\\scriptsize #2}}\n",
+    );
+    // The color names of the events file, in the same shades as the other renderers
+    for name in COLOR_NAMES.iter().copied() {
+        if let Some(hex) = css_color(name) {
+            out.push_str(&format!("\\definecolor{{ecal{}}}{{HTML}}{{{}}}\n", name, hex.trim_start_matches('#').to_uppercase()));
+        }
+    }
+    out.push_str("\\begin{document}\n");
+
+    for (idx, month_start) in displayed_months(config).into_iter().enumerate() {
+        if idx > 0 { out.push_str("\\newpage\n"); }
+        render_month(&mut out, month_start, config, events);
+    }
+
+    out.push_str("\\end{document}\n");
+    out
+}
+
+fn render_month(out: &mut String, month_start: NaiveDate, config: &Config, events: &[Event]) {
+    let weeks = month_weeks(month_start, config.monday_first);
+    out.push_str(&format!("\\begin{{center}}{{\\Huge\\bfseries {} {}}}\\end{{center}}\n", month_name(month_start.month()), month_start.year()));
+    out.push_str(&format!("\\setlength{{\\dayheight}}{{\\dimexpr(\\textheight-3.5cm)/{}\\relax}}\n", weeks.len()));
+
+    let week_column = if config.show_week_numbers { "|>{\\centering\\arraybackslash}p{0.6cm}" } else { "" };
+    out.push_str(&format!("\\begin{{tabularx}}{{\\textwidth}}{{{}|X|X|X|X|X|X|X|}}\n\\hline\n", week_column));
+
+    let mut header: Vec<String> = Vec::new();
+    if config.show_week_numbers { header.push("\\textcolor{ecalblue}{\\bfseries Wk}".to_string()); }
+    for name in weekday_names(config.monday_first) {
+        let color = if name.starts_with('S') { "ecalred" } else { "black" };
+        header.push(format!("\\multicolumn{{1}}{{c|}}{{\\textcolor{{{}}}{{\\bfseries {}}}}}", color, name));
+    }
+    out.push_str(&format!("{} \\\\\n\\hline\n", header.join(" & ")));
+
+    for (week_number, days) in weeks {
+        let mut cells: Vec<String> = Vec::new();
+        if config.show_week_numbers { cells.push(format!("\\textcolor{{ecalblue}}{{{}}}", week_number)); }
+        for day in days {
+            cells.push(match day {
+                Some(date) => day_cell(date, events_on(events, date)),
+                None => "\\daycell{}{}".to_string(),
+            });
+        }
+        out.push_str(&format!("{} \\\\\n\\hline\n", cells.join(" & ")));
+    }
+    out.push_str("\\end{tabularx}\n");
+}
+
+// The day number (red on weekends) with the events below it; the first event's background
+// color lightly shades the cell, like the terminal grid styles the day with it
+fn day_cell(date: NaiveDate, day_events: &[Event]) -> String {
+    let is_weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+    let number = if is_weekend { format!("\\textcolor{{ecalred}}{{\\large\\bfseries {}}}", date.day()) } else { format!("{{\\large\\bfseries {}}}", date.day()) };
+
+    let mut lines = Vec::new();
+    for event in day_events {
+        let text = escape(&full_description(event));
+        match event.fg_color.as_deref().filter(|c| is_known_color(c)).map(str::to_lowercase) {
+            Some(color) => lines.push(format!("\\textcolor{{ecal{}}}{{{}}}", color, text)),
+            None => lines.push(text),
+        }
+    }
+
+    let shading = day_events.first().and_then(|e| e.bg_color.as_deref()).filter(|c| is_known_color(c)).map(str::to_lowercase)
+        .map(|color| format!("\\cellcolor{{ecal{}!20}}", color)).unwrap_or_default();
+    format!("{}\\daycell{{{}}}{{{}}}", shading, number, lines.join("\\par "))
+}
+
+// Escapes LaTeX special characters. Emoji are left out: the standard fonts have no glyphs for them.
+fn escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => { out.push('\\'); out.push(c); }
+            '^' => out.push_str("\\textasciicircum{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '\u{1F000}'..='\u{1FAFF}' | '\u{2600}'..='\u{27BF}' | '\u{FE00}'..='\u{FE0F}' | '\u{200D}' => {}
+            c => out.push(c),
+        }
+    }
+    out.trim().to_string()
+}
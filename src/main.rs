@@ -1,6 +1,27 @@
-use chrono::{Datelike, Duration, NaiveDate, Weekday};
+mod bsdcal;
+mod csv;
+mod edit;
+mod emacs;
+mod find;
+mod fmt;
+mod holidays;
+mod history;
+mod html;
+mod ics;
+mod json;
+mod latex;
+mod markdown;
+mod parser;
+mod remind;
+mod render;
+mod store;
+mod svg;
+mod vcard;
+
+use chrono::{Datelike, Days, Duration, Months, NaiveDate, Weekday};
+use parser::{CategoryDef, ErrorKind, EventLine, Line, ParseError, Rule};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{self, BufRead};
 
 #[derive(Debug, Clone)]
 struct Event {
@@ -9,10 +30,17 @@ struct Event {
     category: Option<String>,
     fg_color: Option<String>,
     bg_color: Option<String>,
+    label: Option<String>,
+    priority: i32,
     original_year: Option<i32>,
+    count_format: Option<String>,
+    rule: Rule,
+    rule_text: String,
+    source: String, // File (or holiday pack) and line the rule came from
+    line: usize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Config {
     num_months: usize,
     start_month: u32,
@@ -22,6 +50,46 @@ struct Config {
     show_events: bool,
     num_columns: usize,
     show_week_numbers: bool,
+    holidays: Vec<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    output_format: OutputFormat,
+    only_categories: Vec<String>,
+    exclude_categories: Vec<String>,
+    window: Option<(NaiveDate, NaiveDate)>, // Exact days of --from/--to, --next, --past and --count
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    JsonLines,
+    Html,
+    Svg,
+    Latex,
+    Markdown,
+    Plain,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FileFormat {
+    Ics,
+    Csv,
+    Remind,
+    BsdCalendar,
+    Vcard,
+    Diary,
+    Org,
+}
+
+// What an importer produced: events file lines plus notes for the summary
+#[derive(Debug, Default)]
+struct ImportReport {
+    lines: Vec<String>,
+    recurring: usize,
+    one_off: usize,
+    as_fixed_dates: Vec<String>, // Recurring events that had no native rule equivalent
+    skipped: Vec<String>,
 }
 
 impl Default for Config {
@@ -36,6 +104,13 @@ impl Default for Config {
             show_events: true,
             num_columns: 3,
             show_week_numbers: true,
+            holidays: Vec::new(),
+            from: None,
+            to: None,
+            output_format: OutputFormat::Text,
+            only_categories: Vec::new(),
+            exclude_categories: Vec::new(),
+            window: None,
         }
     }
 }
@@ -43,6 +118,22 @@ impl Default for Config {
 fn main() {
     let mut config = Config::default();
     let mut events_file = String::from("events.txt");
+    let mut positionals: Vec<String> = Vec::new();
+    let mut file_format: Option<FileFormat> = None;
+    let mut dry_run = false;
+    let mut csv_mapping = csv::CsvMapping::default();
+    let mut output_file: Option<String> = None;
+    let mut paper = svg::PaperSize::default();
+    let mut line_fields = edit::LineFields::default();
+    let mut section: Option<String> = None;
+    let mut show_ids = false;
+    let mut check_only = false;
+    let mut sort_rules = false;
+    let mut use_regex = false;
+    let mut find_years = find::DEFAULT_YEARS;
+    let mut next_until: Option<NaiveDate> = None;
+    let mut past_since: Option<NaiveDate> = None;
+    let mut event_count: Option<usize> = None;
 
     // FIX: Make the iterator peekable so we can check the next arg without consuming it
     let mut args = std::env::args().skip(1).peekable();
@@ -87,6 +178,128 @@ fn main() {
                     events_file = val;
                 }
             }
+            "-hol" | "--holidays" => {
+                if let Some(val) = args.next() {
+                    if val == "list" { holidays::print_pack_list(); return; }
+                    config.holidays.extend(val.split(',').map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()));
+                }
+            }
+            "--only" | "--exclude" => {
+                if let Some(val) = args.next() {
+                    let list = if arg == "--only" { &mut config.only_categories } else { &mut config.exclude_categories };
+                    list.extend(val.split(',').map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()));
+                }
+            }
+            "--print-holidays" => {
+                if let Some(val) = args.next() {
+                    let codes: Vec<String> = val.split(',').map(|s| s.trim().to_lowercase()).filter(|s| !s.is_empty()).collect();
+                    for pack in holidays::selected_packs(&codes) {
+                        println!("{}", pack.rules);
+                    }
+                }
+                return;
+            }
+            "--from" | "--to" => {
+                if let Some(val) = args.next() {
+                    match NaiveDate::parse_from_str(&val, "%Y-%m-%d") {
+                        Ok(date) if arg == "--from" => config.from = Some(date),
+                        Ok(date) => config.to = Some(date),
+                        Err(_) => eprintln!("Warning: Invalid date '{}' for {}. Expected YYYY-MM-DD.", val, arg),
+                    }
+                }
+            }
+            "--next" | "--past" => {
+                if let Some(val) = args.next() {
+                    let today = chrono::Local::now().naive_local().date();
+                    match offset_date(today, &val, arg == "--next") {
+                        Some(date) if arg == "--next" => next_until = Some(date),
+                        Some(date) => past_since = Some(date),
+                        None => eprintln!("Warning: Invalid span '{}' for {}. Expected e.g. 14d, 2w, 3m or 1y.", val, arg),
+                    }
+                }
+            }
+            "--count" => {
+                if let Some(val) = args.next() {
+                    match val.parse::<usize>() {
+                        Ok(n) if n > 0 => event_count = Some(n),
+                        _ => eprintln!("Warning: Invalid count '{}'. Expected a positive number.", val),
+                    }
+                }
+            }
+            "--format" => {
+                if let Some(val) = args.next() {
+                    config.output_format = match val.to_lowercase().as_str() {
+                        "text" => OutputFormat::Text,
+                        "json" => OutputFormat::Json,
+                        "jsonl" | "jsonlines" => OutputFormat::JsonLines,
+                        "html" => OutputFormat::Html,
+                        "svg" => OutputFormat::Svg,
+                        "latex" | "tex" => OutputFormat::Latex,
+                        "markdown" | "md" => OutputFormat::Markdown,
+                        "plain" => OutputFormat::Plain,
+                        _ => {
+                            eprintln!("Warning: Unknown format '{}'. Using text.", val);
+                            OutputFormat::Text
+                        }
+                    };
+                }
+            }
+            "--ics" => file_format = Some(FileFormat::Ics),
+            "--csv" => file_format = Some(FileFormat::Csv),
+            "--remind" => file_format = Some(FileFormat::Remind),
+            "--bsd-calendar" => file_format = Some(FileFormat::BsdCalendar),
+            "--vcard" => file_format = Some(FileFormat::Vcard),
+            "--diary" => file_format = Some(FileFormat::Diary),
+            "--org" => file_format = Some(FileFormat::Org),
+            "--date-col" | "--date-format" | "--desc-col" | "--category-col" | "--delimiter" => {
+                if let Some(val) = args.next() {
+                    match arg.as_str() {
+                        "--date-col" => csv_mapping.date_column = val,
+                        "--date-format" => csv_mapping.date_format = val,
+                        "--desc-col" => csv_mapping.description_column = val,
+                        "--category-col" => csv_mapping.category_column = Some(val),
+                        _ => csv_mapping.delimiter = val.chars().next().unwrap_or(','),
+                    }
+                }
+            }
+            "--dry-run" => dry_run = true,
+            "--ids" => show_ids = true,
+            "--check" => check_only = true,
+            "--sort" => sort_rules = true,
+            "--regex" => use_regex = true,
+            "--years" => {
+                if let Some(val) = args.next() {
+                    find_years = val.parse().ok().filter(|n| (0..=100).contains(n)).unwrap_or_else(|| {
+                        eprintln!("Warning: Invalid number of years '{}'. Using {}.", val, find::DEFAULT_YEARS);
+                        find::DEFAULT_YEARS
+                    });
+                }
+            }
+            "--category" | "--fg" | "--bg" | "--desc" | "--rule" | "--section" => {
+                if let Some(val) = args.next() {
+                    match arg.as_str() {
+                        "--category" => line_fields.category = Some(val),
+                        "--fg" => line_fields.fg_color = Some(val),
+                        "--bg" => line_fields.bg_color = Some(val),
+                        "--desc" => line_fields.description = Some(val),
+                        "--rule" => line_fields.rule = Some(val),
+                        _ => section = Some(val),
+                    }
+                }
+            }
+            "--paper" => {
+                if let Some(val) = args.next() {
+                    paper = svg::parse_paper_size(&val).unwrap_or_else(|| {
+                        eprintln!("Warning: Unknown paper size '{}'. Using A4 landscape.", val);
+                        svg::PaperSize::default()
+                    });
+                }
+            }
+            "-o" | "--output" => {
+                if let Some(val) = args.next() {
+                    output_file = Some(val);
+                }
+            }
             "-sun" | "--sunday-first" => config.monday_first = false,
             "-mon" | "--monday-first" => config.monday_first = true,
             "-c" | "--calendar-only" => { config.show_calendar = true; config.show_events = false; }
@@ -111,24 +324,223 @@ fn main() {
                 }
             }
             "-h" | "--help" => { print_help(); return; }
+            _ if !arg.starts_with('-') => positionals.push(arg),
             _ => {}
         }
     }
 
-    let events = load_events(&events_file, &config);
+    // --from/--to, or --next, --past and --count counted from today, choose exact days: the calendar
+    // shows every month they touch and the events list just those days. An open end of --from/--to
+    // is the edge of the months -m, -y and -n would show; --count alone looks up to a year ahead.
+    if next_until.is_some() || past_since.is_some() || event_count.is_some() {
+        let today = chrono::Local::now().naive_local().date();
+        let until = next_until.or_else(|| event_count.and_then(|_| offset_date(today, "1y", true))).unwrap_or(today);
+        config.window = Some((past_since.unwrap_or(today), until));
+    } else if config.from.is_some() || config.to.is_some() {
+        let from = config.from.unwrap_or_else(|| displayed_range(&config).0);
+        let months_from = Config { start_year: from.year(), start_month: from.month(), ..config.clone() };
+        let to = config.to.unwrap_or_else(|| displayed_range(&months_from).1.pred_opt().unwrap_or(from));
+        if to < from {
+            eprintln!("Error: --to ({}) is before --from ({}).", to, from);
+            std::process::exit(2);
+        }
+        config.window = Some((from, to));
+    }
+    show_months(&mut config);
+
+    match positionals.first().map(String::as_str) {
+        None => {}
+        Some("check") => {
+            if !run_check(&events_file, &config) { std::process::exit(1); }
+            return;
+        }
+        Some("add") => {
+            if let Some(rule) = positionals.get(1) { line_fields.rule = Some(rule.clone()); }
+            if positionals.len() > 2 { line_fields.description = Some(positionals[2..].join(" ")); }
+            if let Err(e) = edit::run_add(&events_file, &line_fields, section.as_deref()) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("list") => {
+            if let Err(e) = edit::run_list(&events_file, show_ids) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("remove") => {
+            let Some(id) = positionals.get(1) else {
+                eprintln!("Error: Usage: ecal remove <ID>; 'ecal list --ids' shows the ids.");
+                std::process::exit(2);
+            };
+            if let Err(e) = edit::run_remove(&events_file, id) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("edit") => {
+            let Some(id) = positionals.get(1) else {
+                eprintln!("Error: Usage: ecal edit <ID> [--rule <RULE>] [--desc <DESCRIPTION>] [--category <NAME>] [--fg <COLOR>] [--bg <COLOR>]");
+                std::process::exit(2);
+            };
+            if let Err(e) = edit::run_edit(&events_file, id, &line_fields) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("find") => {
+            let Some(pattern) = positionals.get(1) else {
+                eprintln!("Error: Usage: ecal find <TEXT> [--regex] [--years <N>]");
+                std::process::exit(2);
+            };
+            if let Err(e) = find::run_find(&events_file, &config, pattern, use_regex, find_years) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("fmt") => {
+            match fmt::run_fmt(&events_file, check_only, sort_rules) {
+                Ok(unchanged) => if check_only && !unchanged { std::process::exit(1); },
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+            return;
+        }
+        Some("history") | Some("undo") => {
+            let command = positionals[0].as_str();
+            let version = match positionals.get(1).map(|n| n.parse::<usize>()) {
+                None => None,
+                Some(Ok(n)) if n > 0 => Some(n),
+                Some(_) => {
+                    eprintln!("Error: Usage: ecal {} [N] where N is a change number from 'ecal history'.", command);
+                    std::process::exit(2);
+                }
+            };
+            let result = if command == "undo" {
+                history::run_undo(&events_file, version.unwrap_or(1))
+            } else {
+                history::run_history(&events_file, version)
+            };
+            if let Err(e) = result {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("export") => {
+            let Some(format) = file_format else {
+                eprintln!("Error: Choose an export format, e.g. 'ecal export --ics' or 'ecal export --csv'.");
+                std::process::exit(2);
+            };
+            if let Err(e) = run_export(&events_file, &config, format, output_file.as_deref()) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some("import") => {
+            let (Some(format), Some(input)) = (file_format, positionals.get(1)) else {
+                eprintln!("Error: Usage: ecal import --ics|--csv|--remind|--bsd-calendar|--diary|--org|--vcard <FILE> [--dry-run]");
+                std::process::exit(2);
+            };
+            if let Err(e) = run_import(&events_file, &config, format, input, &csv_mapping, dry_run) {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        Some(other) => {
+            eprintln!("Error: Unknown command '{}'. Use --help to see available commands.", other);
+            std::process::exit(2);
+        }
+    }
+
+    // The SVG output is a year planner, twelve months from the start month unless --from/--to or
+    // --next/--past pick the months
+    if config.output_format == OutputFormat::Svg && config.window.is_none() {
+        config.num_months = 12;
+    }
 
-    if config.show_calendar {
-        display_calendars(&config, &events);
+    let mut events = load_events(&events_file, &config);
+    events.retain(|e| category_selected(&config, e));
+    if config.window.is_some() {
+        select_window(&mut config, &mut events, event_count);
     }
-    if config.show_events {
-        display_events_list(&config, &events);
+
+    let content = match config.output_format {
+        OutputFormat::Text => {
+            if config.show_calendar {
+                print!("{}", format_calendars(&config, &events, true));
+            }
+            if config.show_events {
+                print!("{}", format_events_list(&config, &events, true));
+            }
+            return;
+        }
+        OutputFormat::Json | OutputFormat::JsonLines => {
+            let (filtered_events, start_date, end_date) = events_in_display_range(&config, &events);
+            let last_date = end_date.pred_opt().unwrap_or(end_date);
+            json::events_to_json(&filtered_events, start_date, last_date, config.output_format == OutputFormat::JsonLines)
+        }
+        OutputFormat::Html => html::render_html(&config, &events),
+        OutputFormat::Svg => svg::render_svg(&config, &events, paper),
+        OutputFormat::Latex => latex::render_latex(&config, &events),
+        OutputFormat::Markdown => markdown::render_markdown(&config, &events),
+        OutputFormat::Plain => markdown::render_plain(&config, &events),
+    };
+    if let Err(e) = write_output(output_file.as_deref(), &content) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// Writes generated output to a file given with -o, or to stdout
+fn write_output(output_file: Option<&str>, content: &str) -> Result<(), String> {
+    match output_file {
+        Some(path) => fs::write(path, content).map_err(|e| format!("Cannot write '{}': {}", path, e)),
+        None => {
+            print!("{}", content);
+            Ok(())
+        }
     }
 }
 
 fn print_help() {
     println!("\nCalendar with Events");
     println!("----------------------------------------------------------------------------------");
-    println!("\x1b[1m\x1b[33mUsage: ecal [OPTIONS]\x1b[0m");
+    println!("\x1b[1m\x1b[33mUsage: ecal [COMMAND] [OPTIONS]\x1b[0m");
+    println!("\x1b[1mCommands:\x1b[0m");
+    println!("   \x1b[34madd\x1b[0m      Append a rule: \x1b[32madd <RULE> <DESCRIPTION>\x1b[0m [\x1b[32m--category\x1b[0m <NAME>] [\x1b[32m--fg\x1b[0m/\x1b[32m--bg\x1b[0m <COLOR>]");
+    println!("            [\x1b[32m--section\x1b[0m <NAME>] to add it at the end of the section whose header comment mentions NAME");
+    println!("   \x1b[34mcheck\x1b[0m    Validate the events file and report every problem with file, line and column");
+    println!("   \x1b[34medit\x1b[0m     Change one rule line in place: \x1b[32medit <ID>\x1b[0m [\x1b[32m--rule\x1b[0m <RULE>] [\x1b[32m--desc\x1b[0m <TEXT>] [\x1b[32m--category\x1b[0m <NAME>]");
+    println!("            [\x1b[32m--fg\x1b[0m/\x1b[32m--bg\x1b[0m <COLOR>]; an empty value removes a category or color;");
+    println!("            the id comes from the rule, category and description, so editing them gives the line a new id");
+    println!("   \x1b[34mexport\x1b[0m   Export events (\x1b[32m--ics\x1b[0m, \x1b[32m--csv\x1b[0m) for the displayed months or \x1b[32m--from\x1b[0m/\x1b[32m--to\x1b[0m, to stdout or \x1b[32m-o <PATH>\x1b[0m;");
+    println!("            \x1b[32m--remind\x1b[0m, \x1b[32m--bsd-calendar\x1b[0m, \x1b[32m--diary\x1b[0m and \x1b[32m--org\x1b[0m export the rules themselves");
+    println!("   \x1b[34mfind\x1b[0m     Show the previous and next dates of events whose description or category contains TEXT:");
+    println!("            \x1b[32mfind <TEXT>\x1b[0m, case-insensitive; \x1b[32m--regex\x1b[0m for a regular expression, \x1b[32m--years <N>\x1b[0m around today (2)");
+    println!("   \x1b[34mfmt\x1b[0m      Rewrite the events file in the canonical layout, rules of each block aligned;");
+    println!("            \x1b[32m--sort\x1b[0m puts the rules of each block in calendar order, \x1b[32m--check\x1b[0m only reports (exit 1 if unformatted)");
+    println!("   \x1b[34mhistory\x1b[0m  List the changes ecal made to the events file; \x1b[32mhistory <N>\x1b[0m shows what change N did");
+    println!("   \x1b[34mimport\x1b[0m   Append events from a file (\x1b[32m--ics\x1b[0m, \x1b[32m--csv\x1b[0m, \x1b[32m--remind\x1b[0m, \x1b[32m--bsd-calendar\x1b[0m, \x1b[32m--diary\x1b[0m,");
+    println!("            \x1b[32m--org\x1b[0m or \x1b[32m--vcard <FILE>\x1b[0m) to the events file;");
+    println!("            \x1b[32m--dry-run\x1b[0m only prints them; \x1b[32m--vcard\x1b[0m imports birthdays and anniversaries of contacts");
+    println!("            CSV columns: \x1b[32m--date-col\x1b[0m (date), \x1b[32m--date-format\x1b[0m (%Y-%m-%d), \x1b[32m--desc-col\x1b[0m (description),");
+    println!("            \x1b[32m--category-col\x1b[0m (category), \x1b[32m--delimiter\x1b[0m (,); columns by header name or number");
+    println!("   \x1b[34mlist\x1b[0m     List the rule lines of the events file; \x1b[32m--ids\x1b[0m adds the ids used by edit and remove;");
+    println!("            an id stays the same when other lines change, but changes when its rule, category or description is edited");
+    println!("   \x1b[34mremove\x1b[0m   Remove one rule line: \x1b[32mremove <ID>\x1b[0m");
+    println!("   \x1b[34mundo\x1b[0m     Restore the events file to before the last change, or \x1b[32mundo <N>\x1b[0m to before change N;");
+    println!("            the last 20 versions are kept in .ecal-backups next to the file");
+    println!("\x1b[1mOptions:\x1b[0m");
     println!(" \x1b[1m\x1b[34m -m\x1b[0m    | \x1b[34m--month        \x1b[0m \x1b[32m<MONTH>\x1b[0m  Start month");
     println!(" \x1b[1m\x1b[34m -y\x1b[0m    | \x1b[34m--year         \x1b[0m \x1b[32m<YEAR>\x1b[0m   Start year");
     println!(" \x1b[1m\x1b[34m -n\x1b[0m    | \x1b[34m--num-months   \x1b[0m \x1b[32m<NUM>\x1b[0m    Number of months to display (1-12)");
@@ -138,242 +550,363 @@ fn print_help() {
     println!(" \x1b[1m\x1b[34m -w\x1b[0m    | \x1b[34m--weeks        \x1b[0m \x1b[32m[on|off]\x1b[0m Show week numbers (default: on)");
     println!(" \x1b[1m\x1b[34m -c\x1b[0m    | \x1b[34m--calendar-only\x1b[0m          Show only calendar");
     println!(" \x1b[1m\x1b[34m -e\x1b[0m    | \x1b[34m--events-only  \x1b[0m          Show only events");
+    println!("       | \x1b[34m--format       \x1b[0m \x1b[32m<FMT>\x1b[0m    Output format: text (default), json, jsonl (one event per line), plain (no colors),");
+    println!("{}markdown, html, svg (year planner) or latex", " ".repeat(34));
     println!(" \x1b[1m\x1b[34m -f\x1b[0m    | \x1b[34m--file         \x1b[0m \x1b[32m<PATH>\x1b[0m   Path to events file (default: events.txt)");
+    println!(" \x1b[1m\x1b[34m -hol\x1b[0m  | \x1b[34m--holidays     \x1b[0m \x1b[32m<CODES>\x1b[0m  Built-in holiday packs, e.g. ie,us,de-by (\x1b[32mlist\x1b[0m to show all)");
+    println!("        | \x1b[34m--print-holidays\x1b[0m \x1b[32m<CODES>\x1b[0m Print the rules of holiday packs to copy into your file");
+    println!("       | \x1b[34m--next         \x1b[0m \x1b[32m<SPAN>\x1b[0m   List the events from today to 14d, 2w, 3m or 1y ahead");
+    println!("       | \x1b[34m--past         \x1b[0m \x1b[32m<SPAN>\x1b[0m   List the events from that long ago up to today (with --next, up to its end)");
+    println!("       | \x1b[34m--count        \x1b[0m \x1b[32m<NUM>\x1b[0m    List only the next NUM events from today");
+    println!("       | \x1b[34m--only         \x1b[0m \x1b[32m<CATS>\x1b[0m   Show only events of these categories, e.g. ie,church");
+    println!("       | \x1b[34m--exclude      \x1b[0m \x1b[32m<CATS>\x1b[0m   Hide events of these categories, e.g. fun");
+    println!("       | \x1b[34m--from         \x1b[0m \x1b[32m<DATE>\x1b[0m   First day to show or export (YYYY-MM-DD)");
+    println!("       | \x1b[34m--to           \x1b[0m \x1b[32m<DATE>\x1b[0m   Last day to show or export (YYYY-MM-DD)");
+    println!(" \x1b[1m\x1b[34m -o\x1b[0m    | \x1b[34m--output       \x1b[0m \x1b[32m<PATH>\x1b[0m   Write exports and rendered output to a file instead of stdout");
+    println!("       | \x1b[34m--paper        \x1b[0m \x1b[32m<SIZE>\x1b[0m   SVG paper: a2-a5, letter, legal, tabloid (add -portrait) or WxH in mm");
     println!(" \x1b[1m\x1b[34m -h\x1b[0m    | \x1b[34m--help         \x1b[0m          Display this help message");
 }
 
-fn parse_fixed_date_rule(rule: &str) -> Option<NaiveDate> {
-    if let Ok(date) = NaiveDate::parse_from_str(rule, "%d-%m-%Y") { return Some(date); }
-    if let Ok(date) = NaiveDate::parse_from_str(rule, "%m/%d/%Y") { return Some(date); }
-    if let Ok(date) = NaiveDate::parse_from_str(rule, "%Y-%m-%d") { return Some(date); }
-    None
+// The event lines of the selected holiday packs and the events file, in that order, with the
+// category declarations of all of them
+struct RuleSet {
+    event_lines: Vec<(String, usize, EventLine)>, // Source, line number and the parsed line
+    categories: HashMap<String, CategoryDef>,
 }
 
-fn load_events(filename: &str, config: &Config) -> Vec<Event> {
-    let mut events = Vec::new();
-    let total_months_from_epoch = config.start_year as i64 * 12 + config.start_month as i64 + config.num_months as i64;
-    let end_year_check = ((total_months_from_epoch - 1) / 12) as i32;
-
-    if let Ok(file) = fs::File::open(filename) {
-        let mut reader = io::BufReader::new(file);
-
-        let mut line = String::new();
-        while let Ok(bytes_read) = reader.read_line(&mut line) {
-            if bytes_read == 0 { break; } // EOF
-
-            let trimmed_line = line.trim();
-            if trimmed_line.is_empty() || trimmed_line.starts_with('#') {
-                line.clear();
-                continue;
-            }
-
-            let parts: Vec<&str> = trimmed_line.splitn(2, ';').collect();
-            let rule_part = parts[0].trim();
-            let mut category: Option<String> = None;
-            let mut fg_color: Option<String> = None;
-            let mut bg_color: Option<String> = None;
-
-            let description_text = if parts.len() > 1 {
-                let rest = parts[1].trim();
-                if rest.starts_with('[') {
-                    if let Some(end_bracket) = rest.find(']') {
-                        let meta_block = &rest[1..end_bracket];
-                        let meta_parts: Vec<&str> = meta_block.split(',').map(|s| s.trim()).collect();
-                        if let Some(cat) = meta_parts.first() { if !cat.is_empty() { category = Some(cat.to_string()); } }
-                        if let Some(fg) = meta_parts.get(1) { if !fg.is_empty() { fg_color = Some(fg.to_string()); } }
-                        if let Some(bg) = meta_parts.get(2) { if !bg.is_empty() { bg_color = Some(bg.to_string()); } }
-                        rest[end_bracket + 1..].trim().to_string()
-                    } else { rest.to_string() }
-                } else { rest.to_string() }
-            } else {
-                match rule_part.split_once(char::is_whitespace) {
-                    Some((_, desc)) => desc.trim().to_string(),
-                    None => "".to_string(),
-                }
-            };
+fn load_rules(filename: &str, config: &Config) -> RuleSet {
+    let mut errors = Vec::new();
 
-            let years_to_check = config.start_year..=end_year_check;
-            let mut base_date: Option<NaiveDate> = None;
-            let mut is_anniversary_rule = false;
+    let mut parsed_packs = Vec::new();
+    for pack in holidays::selected_packs(&config.holidays) {
+        let name = holidays::source_name(pack);
+        let (lines, pack_errors) = parser::parse_source(&name, pack.rules);
+        errors.extend(pack_errors);
+        parsed_packs.push((name, lines));
+    }
 
-            if let Some(date) = parse_fixed_date_rule(rule_part) {
-                base_date = Some(date);
-                if let Some(ref cat) = category {
-                    if cat == "bday" || cat == "anni" { is_anniversary_rule = true; }
-                }
-                if !is_anniversary_rule {
-                    if (config.start_year..=end_year_check).contains(&date.year()) {
-                        if let Some(d) = NaiveDate::from_ymd_opt(date.year(), date.month(), date.day()) {
-                            events.push(Event { date: d, description: description_text.clone(), category: category.clone(), fg_color: fg_color.clone(), bg_color: bg_color.clone(), original_year: None });
-                        }
-                    }
-                    line.clear();
-                    continue;
-                }
-            }
+    let parsed_file = match fs::read(filename) {
+        Ok(bytes) => {
+            let (lines, file_errors) = parser::parse_source(filename, &String::from_utf8_lossy(&bytes));
+            errors.extend(file_errors);
+            lines
+        }
+        Err(_) => {
+            eprintln!("Info: Event file '{}' not found. Continuing without events.", filename);
+            Vec::new()
+        }
+    };
 
-            let mut added_dates = std::collections::HashSet::new();
-            for year in years_to_check {
-                let mut date_to_add: Option<NaiveDate> = None;
-                let mut original_year_to_store: Option<i32> = None;
+    if !errors.is_empty() {
+        eprintln!("Warning: {} problem(s) found in the events. Run 'ecal check' for details.", errors.len());
+    }
 
-                if is_anniversary_rule {
-                    let bd = base_date.unwrap();
-                    if year >= bd.year() {
-                        date_to_add = NaiveDate::from_ymd_opt(year, bd.month(), bd.day());
-                        original_year_to_store = Some(bd.year());
-                    }
-                } else if base_date.is_none() {
-                    date_to_add = calculate_date_from_rule(rule_part, year);
-                }
+    // A rule in the user's file overrides the same rule of the same category in a holiday pack, so
+    // e.g. an Irish 1/1 line doesn't hide the German New Year
+    let user_rules: HashSet<(String, Option<String>)> = parsed_file.iter().filter_map(|(_, line)| match line {
+        Line::Event(event_line) => Some((event_line.rule_text.clone(), event_line.category.clone())),
+        _ => None,
+    }).collect();
 
-                if let Some(date) = date_to_add {
-                    if added_dates.insert(date) {
-                        events.push(Event { date, description: description_text.clone(), category: category.clone(), fg_color: fg_color.clone(), bg_color: bg_color.clone(), original_year: original_year_to_store });
-                    }
-                }
-            }
+    // Category declarations apply to every source; the user's file is read last so it wins
+    let mut categories: HashMap<String, CategoryDef> = HashMap::new();
+    for (name, count_format) in [("bday", "{nth} Birthday"), ("anni", "{nth} Anniversary")] {
+        categories.insert(name.to_string(), CategoryDef { count_format: Some(count_format.to_string()), ..Default::default() });
+    }
+    for (_, line) in parsed_packs.iter().flat_map(|(_, lines)| lines).chain(&parsed_file) {
+        if let Line::Category { name, def } = line {
+            categories.entry(name.clone()).or_default().merge(def);
+        }
+    }
 
-            line.clear();
+    let mut event_lines = Vec::new();
+    for (name, lines) in parsed_packs {
+        for (line_no, line) in lines {
+            if let Line::Event(event_line) = line && !user_rules.contains(&(event_line.rule_text.clone(), event_line.category.clone())) {
+                event_lines.push((name.clone(), line_no, event_line));
+            }
         }
-    } else {
-        eprintln!("Info: Event file '{}' not found. Continuing without events.", filename);
+    }
+    for (line_no, line) in parsed_file {
+        if let Line::Event(event_line) = line {
+            event_lines.push((filename.to_string(), line_no, event_line));
+        }
+    }
+    RuleSet { event_lines, categories }
+}
+
+fn load_events(filename: &str, config: &Config) -> Vec<Event> {
+    let rules = load_rules(filename, config);
+    let mut events = Vec::new();
+    for (source, line_no, event_line) in &rules.event_lines {
+        expand_event_line(event_line, (source, *line_no), config, &rules.categories, &mut events);
     }
 
-    events.sort_by_key(|e| e.date);
+    // Within a day, higher priority events come first so the calendar grid styles the day with them
+    events.sort_by_key(|e| (e.date, std::cmp::Reverse(e.priority)));
     events
 }
 
-fn calculate_date_from_rule(rule: &str, year: i32) -> Option<NaiveDate> {
-    let rule = rule.trim();
-    if rule.starts_with('E') {
-        let offset = if rule == "E" { 0 } else { rule[1..].parse::<i64>().ok()? };
-        return calculate_easter_date(year).map(|date| date + Duration::days(offset));
-    }
-    if let Some(hash_pos) = rule.find('#') {
-        let date_part = &rule[0..hash_pos];
-        let n_str = &rule[hash_pos + 1..];
-        let mut parts = date_part.split('/');
-        let month = parts.next()?.parse::<u32>().ok()?;
-        let dow_num = parts.next()?.parse::<u32>().ok()?;
-        let dow_num = if dow_num == 0 { 7 } else { dow_num };
-        let n = n_str.parse::<u32>().ok()?;
-        return find_nth_dow(year, month, dow_num, n);
-    }
-    if let Some(q_pos) = rule.find('?') {
-        let date_part = &rule[0..q_pos];
-        let condition_part = &rule[q_pos + 1..];
-        let mut parts = date_part.split('/');
-        let month = parts.next()?.parse::<u32>().ok()?;
-        let day = parts.next()?.parse::<u32>().ok()?;
-        let target_date = NaiveDate::from_ymd_opt(year, month, day)?;
-        if condition_part.len() >= 3 {
-            let target_dow_num = condition_part.chars().next()?.to_digit(10)?;
-            let operator = condition_part.chars().nth(1)?;
-            let offset = condition_part[2..].parse::<i64>().ok()?;
-            let target_weekday = match target_dow_num {
-                0 => Weekday::Sun, 1 => Weekday::Mon, 2 => Weekday::Tue, 3 => Weekday::Wed,
-                4 => Weekday::Thu, 5 => Weekday::Fri, 6 => Weekday::Sat, _ => return None,
-            };
-            if target_date.weekday() == target_weekday {
-                let duration = Duration::days(offset);
-                let final_offset = match operator { '+' => duration, '-' => -duration, _ => return None };
-                return Some(target_date + final_offset);
+fn expand_event_line(event_line: &EventLine, (source, line): (&str, usize), config: &Config, categories: &HashMap<String, CategoryDef>, events: &mut Vec<Event>) {
+    let (_, end_date) = displayed_range(config);
+    let end_year_check = end_date.pred_opt().unwrap_or(end_date).year();
+
+    let category_def = event_line.category.as_ref().and_then(|c| categories.get(c));
+    let count_format = category_def.and_then(|d| d.count_format.clone());
+    let make_event = |date: NaiveDate, original_year: Option<i32>| Event {
+        date,
+        description: event_line.description.clone(),
+        category: event_line.category.clone(),
+        fg_color: event_line.fg_color.clone().or_else(|| category_def.and_then(|d| d.fg_color.clone())),
+        bg_color: event_line.bg_color.clone().or_else(|| category_def.and_then(|d| d.bg_color.clone())),
+        label: category_def.and_then(|d| d.label.clone()),
+        priority: category_def.and_then(|d| d.priority).unwrap_or(0),
+        original_year,
+        count_format: original_year.and(count_format.clone()),
+        rule: event_line.rule,
+        rule_text: event_line.rule_text.clone(),
+        source: source.to_string(),
+        line,
+    };
+
+    if let Rule::Fixed(date) = event_line.rule {
+        if count_format.is_none() {
+            if (config.start_year..=end_year_check).contains(&date.year()) {
+                events.push(make_event(date, None));
+            }
+            return;
+        }
+        // Counting categories (birthdays, anniversaries...) recur every year from the original date
+        for year in config.start_year.max(date.year())..=end_year_check {
+            if let Some(d) = NaiveDate::from_ymd_opt(year, date.month(), date.day()) {
+                events.push(make_event(d, Some(date.year())));
             }
         }
-        if condition_part.is_empty() || condition_part.chars().all(|c| c.is_digit(10)) { return Some(target_date); }
-        return None;
+        return;
     }
-    if rule.contains('/') && rule.chars().filter(|c| *c == '/').count() == 1 {
-        let mut parts = rule.split('/');
-        let month = parts.next()?.parse::<u32>().ok()?;
-        let day = parts.next()?.parse::<u32>().ok()?;
-        return NaiveDate::from_ymd_opt(year, month, day);
+
+    let mut added_dates = HashSet::new();
+    for year in config.start_year..=end_year_check {
+        if let Some(date) = event_line.rule.date_in_year(year) && added_dates.insert(date) {
+            events.push(make_event(date, None));
+        }
+    }
+}
+
+// "14d", "2w", "3m" or "1y" after (or before) `date`; a bare number counts days
+fn offset_date(date: NaiveDate, span: &str, forward: bool) -> Option<NaiveDate> {
+    let span = span.trim().to_lowercase();
+    let (number, unit) = match span.char_indices().last()? {
+        (idx, unit @ ('d' | 'w' | 'm' | 'y')) => (&span[..idx], unit),
+        _ => (span.as_str(), 'd'),
+    };
+    let n: u32 = number.parse().ok()?;
+    let (days, months) = match unit {
+        'w' => (n as u64 * 7, 0),
+        'm' => (0, n),
+        'y' => (0, n.checked_mul(12)?),
+        _ => (n as u64, 0),
+    };
+    if forward {
+        date.checked_add_days(Days::new(days))?.checked_add_months(Months::new(months))
+    } else {
+        date.checked_sub_days(Days::new(days))?.checked_sub_months(Months::new(months))
     }
-    None
 }
 
-fn calculate_easter_date(year: i32) -> Option<NaiveDate> {
-    if year < 1583 { return None; }
-    let a = year % 19; let b = year / 100; let c = year % 100;
-    let d = b / 4; let e = b % 4; let f = (b + 8) / 25; let g = (b - f + 1) / 3;
-    let h = (19 * a + b - d - g + 15) % 30; let i = c / 4; let k = c % 4;
-    let l = (32 + 2 * e + 2 * i - h - k) % 7; let m = (a + 11 * h + 22 * l) / 451;
-    let month = (h + l - 7 * m + 114) / 31; let day = (h + l - 7 * m + 114) % 31 + 1;
-    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+// Displays every month the window touches
+fn show_months(config: &mut Config) {
+    let Some((from, to)) = config.window else { return };
+    config.start_year = from.year();
+    config.start_month = from.month();
+    config.num_months = ((to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32 + 1).max(1) as usize;
 }
 
-fn find_nth_dow(year: i32, month: u32, dow_num: u32, n: u32) -> Option<NaiveDate> {
-    if n == 0 || n > 5 || dow_num == 0 || dow_num > 7 { return None; }
-    let target_weekday = match dow_num {
-        1 => Weekday::Mon, 2 => Weekday::Tue, 3 => Weekday::Wed, 4 => Weekday::Thu,
-        5 => Weekday::Fri, 6 => Weekday::Sat, 7 => Weekday::Sun, _ => return None,
+// Keeps the events inside the window; with --count only that many from today on, and the window
+// and the months shown end with the last of them
+fn select_window(config: &mut Config, events: &mut Vec<Event>, count: Option<usize>) {
+    let Some((from, to)) = config.window else { return };
+    events.retain(|e| e.date >= from && e.date <= to);
+    let Some(count) = count else { return };
+    let today = chrono::Local::now().naive_local().date();
+    let upcoming = events.partition_point(|e| e.date < today);
+    if events.len() > upcoming + count {
+        events.truncate(upcoming + count);
+        let last = events.last().map_or(today, |e| e.date).max(today);
+        config.window = Some((from, last));
+        show_months(config);
+    }
+}
+
+// First and last day covered by an export: the window of --from/--to or --next/--past, or the
+// displayed months
+fn export_range(config: &Config) -> (NaiveDate, NaiveDate) {
+    config.window.unwrap_or_else(|| {
+        let (start_date, end_date) = displayed_range(config);
+        (start_date, end_date.pred_opt().unwrap_or(end_date))
+    })
+}
+
+fn run_export(events_file: &str, config: &Config, format: FileFormat, output_file: Option<&str>) -> Result<(), String> {
+    let content = match format {
+        FileFormat::Ics => ics::export_ics(&export_events(events_file, config)?),
+        FileFormat::Csv => csv::export_csv(&export_events(events_file, config)?),
+        // remind(1), calendar(1) and Emacs have recurring rules of their own, so the rules are translated
+        // instead of the expanded dates
+        FileFormat::Remind => remind::export_remind(&load_rules(events_file, config)),
+        FileFormat::BsdCalendar => bsdcal::export_calendar(&load_rules(events_file, config)),
+        FileFormat::Diary => emacs::export_diary(&load_rules(events_file, config)),
+        FileFormat::Org => emacs::export_org(&load_rules(events_file, config), config.from.map_or(config.start_year, |d| d.year())),
+        FileFormat::Vcard => return Err("vCards can only be imported.".to_string()),
+    };
+    write_output(output_file, &content)
+}
+
+// Events of the export range
+fn export_events(events_file: &str, config: &Config) -> Result<Vec<Event>, String> {
+    let (from, to) = export_range(config);
+    Ok(load_events(events_file, config).into_iter().filter(|e| e.date >= from && e.date <= to && category_selected(config, e)).collect())
+}
+
+// --only keeps just the listed categories and --exclude drops them; events without a category
+// only go when --only is given
+fn category_selected(config: &Config, event: &Event) -> bool {
+    let category = event.category.as_deref().map(str::to_lowercase);
+    let listed = |list: &[String]| category.as_ref().is_some_and(|c| list.contains(c));
+    (config.only_categories.is_empty() || listed(&config.only_categories)) && !listed(&config.exclude_categories)
+}
+
+fn run_import(events_file: &str, config: &Config, format: FileFormat, input: &str, csv_mapping: &csv::CsvMapping, dry_run: bool) -> Result<(), String> {
+    let content = fs::read(input).map_err(|e| format!("Cannot read '{}': {}", input, e))?;
+    let content = String::from_utf8_lossy(&content);
+
+    // Recurrences without a rule equivalent are expanded from --from (or today) up to --to (or the
+    // end of next year)
+    let today = chrono::Local::now().naive_local().date();
+    let window_start = config.from.unwrap_or(today);
+    let window_end = config.to.unwrap_or_else(|| NaiveDate::from_ymd_opt(today.year() + 1, 12, 31).unwrap_or(today));
+
+    let report = match format {
+        FileFormat::Ics => ics::import_ics(&content, (window_start, window_end)),
+        FileFormat::Csv => csv::import_csv(&content, csv_mapping)?,
+        FileFormat::Remind => remind::import_remind(&content),
+        FileFormat::BsdCalendar => bsdcal::import_calendar(&content),
+        FileFormat::Diary => emacs::import_diary(&content),
+        FileFormat::Org => emacs::import_org(&content),
+        FileFormat::Vcard => vcard::import_vcard(&content, events_file, &String::from_utf8_lossy(&fs::read(events_file).unwrap_or_default())),
     };
-    let mut current_date = NaiveDate::from_ymd_opt(year, month, 1)?;
-    while current_date.month() == month && current_date.weekday() != target_weekday {
-        current_date += Duration::days(1);
+
+    if dry_run {
+        for line in &report.lines { println!("{}", line); }
+    } else if !report.lines.is_empty() {
+        let header = format!("# Imported from {} on {}", input, today.format("%Y-%m-%d"));
+        let lines: Vec<String> = std::iter::once(header).chain(report.lines.iter().cloned()).collect();
+        store::modify(events_file, |content| Ok(store::append_lines(content, &lines)))?;
+    }
+
+    let target = if dry_run { "(dry run)".to_string() } else { format!("into '{}'", events_file) };
+    eprintln!("\x1b[1mImported {} line(s) from '{}' {}:\x1b[0m", report.lines.len(), input, target);
+    if report.recurring > 0 { eprintln!("  {} recurring event(s) as rules", report.recurring); }
+    if report.one_off > 0 { eprintln!("  {} single date(s)", report.one_off); }
+    if !report.as_fixed_dates.is_empty() {
+        eprintln!("  \x1b[33m{} recurring event(s) only as fixed dates:\x1b[0m", report.as_fixed_dates.len());
+        for note in &report.as_fixed_dates { eprintln!("    - {}", note); }
     }
-    if current_date.month() == month {
-        current_date += Duration::weeks((n - 1) as i64);
-        if n == 5 && current_date.month() != month { current_date -= Duration::weeks(1); }
-        if current_date.month() == month { return Some(current_date); }
+    if !report.skipped.is_empty() {
+        eprintln!("  \x1b[31m{} event(s) skipped:\x1b[0m", report.skipped.len());
+        for note in &report.skipped { eprintln!("    - {}", note); }
     }
-    None
+    Ok(())
 }
 
-fn display_calendars(config: &Config, events: &[Event]) {
-    let months_per_row = if config.num_months == 1 { 1 } else { config.num_columns.max(1) };
-    let num_rows = (config.num_months + months_per_row - 1) / months_per_row;
-    for row in 0..num_rows {
-        let start_idx = row * months_per_row;
-        let end_idx = std::cmp::min(start_idx + months_per_row, config.num_months);
-        display_month_row(config, events, start_idx, end_idx);
-        if row < num_rows - 1 { println!(); }
+// Validates the events file (and any selected holiday packs) and prints every problem found.
+// Returns false when there were errors.
+fn run_check(filename: &str, config: &Config) -> bool {
+    let mut sources: Vec<(String, String)> = Vec::new();
+    let mut errors = Vec::new();
+
+    sources.extend(holidays::selected_packs(&config.holidays).into_iter().map(|p| (holidays::source_name(p), p.rules.to_string())));
+
+    match fs::read(filename) {
+        Ok(bytes) => {
+            if let Err(e) = std::str::from_utf8(&bytes) {
+                let line = bytes[..e.valid_up_to()].iter().filter(|b| **b == b'\n').count() + 1;
+                errors.push(ParseError { file: filename.to_string(), line, column: 1, kind: ErrorKind::Unreadable("file is not valid UTF-8".to_string()) });
+            }
+            sources.push((filename.to_string(), String::from_utf8_lossy(&bytes).into_owned()));
+        }
+        Err(e) => errors.push(ParseError { file: filename.to_string(), line: 0, column: 0, kind: ErrorKind::Unreadable(e.to_string()) }),
     }
-}
 
-fn display_month_row(config: &Config, events: &[Event], start_idx: usize, end_idx: usize) {
-    let mut dates = Vec::new();
-    for idx in start_idx..end_idx {
-        let total_months_from_epoch = config.start_year as i64 * 12 + config.start_month as i64 + idx as i64 - 1;
-        let year = (total_months_from_epoch / 12) as i32;
-        let month = (total_months_from_epoch % 12 + 1) as u32;
-        dates.push(NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_else(|| chrono::Local::now().naive_local().date()));
+    let mut num_lines = 0;
+    for (name, content) in &sources {
+        let (_, source_errors) = parser::parse_source(name, content);
+        num_lines += content.lines().count();
+        errors.extend(source_errors);
     }
 
-    let calendar_width = if config.show_week_numbers { 24 } else { 21 };
+    for error in &errors {
+        println!("\x1b[1m{}:\x1b[0m \x1b[1m\x1b[31merror:\x1b[0m {}", error.location(), error.kind);
+        let source_line = sources.iter().find(|(name, _)| *name == error.file).and_then(|(_, content)| content.lines().nth(error.line.wrapping_sub(1)));
+        if let Some(text) = source_line {
+            println!("  \x1b[34m{:>4} |\x1b[0m {}", error.line, text);
+            println!("  \x1b[34m     |\x1b[0m {}\x1b[31m^\x1b[0m", " ".repeat(error.column.saturating_sub(1)));
+        }
+    }
 
-    for (idx, date) in dates.iter().enumerate() {
-        let month_name_str = format!("{} {}", month_name(date.month()), date.year());
-        let padding = (calendar_width - month_name_str.len()) / 2;
-        print!("{}\x1b[1m{}\x1b[0m", " ".repeat(padding), month_name_str);
-        print!("{}", " ".repeat(calendar_width.saturating_sub(padding).saturating_sub(month_name_str.len())));
-        if idx < dates.len() - 1 { print!("    "); }
+    if errors.is_empty() {
+        println!("\x1b[32mOK\x1b[0m: {} line(s) checked, no problems found.", num_lines);
+        true
+    } else {
+        println!("\x1b[31m{} error(s)\x1b[0m found in {} line(s) checked.", errors.len(), num_lines);
+        false
     }
-    println!();
+}
 
-    for idx in 0..dates.len() {
-        print_weekday_header(config);
-        if idx < dates.len() - 1 { print!("     "); }
+// The month grids, `months_per_row` side by side. Without `ansi` this is the plain text output.
+fn format_calendars(config: &Config, events: &[Event], ansi: bool) -> String {
+    let months = displayed_months(config);
+    let mut out = String::new();
+    for (row, row_months) in months.chunks(render::months_per_row(config)).enumerate() {
+        if row > 0 { out.push('\n'); }
+        format_month_row(&mut out, config, events, row_months, ansi);
     }
-    println!();
+    out
+}
 
-    let max_weeks = dates.iter().map(|d| weeks_in_month(*d, config.monday_first)).max().unwrap_or(6);
-    for week in 0..max_weeks {
-        let is_empty_row = dates.iter().all(|&date| {
-            let week_start_day = get_week_start_day(date, week, config.monday_first);
-            let days_in_month = days_in_month(date.year(), date.month());
-            week_start_day > days_in_month as i32 || week_start_day + 6 < 1
-        });
-        if !is_empty_row {
-            for (idx, date) in dates.iter().enumerate() {
-                print_week_row(*date, week, config, events);
-                if idx < dates.len() - 1 { print!("    "); }
+// First day of every displayed month
+fn displayed_months(config: &Config) -> Vec<NaiveDate> {
+    (0..config.num_months).map(|idx| displayed_month(config, idx)).collect()
+}
+
+// First day of the month `idx` months after the start month
+fn displayed_month(config: &Config, idx: usize) -> NaiveDate {
+    let total_months_from_epoch = config.start_year as i64 * 12 + config.start_month as i64 - 1 + idx as i64;
+    let year = (total_months_from_epoch / 12) as i32;
+    let month = (total_months_from_epoch % 12 + 1) as u32;
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_else(|| chrono::Local::now().naive_local().date())
+}
+
+// First day of the displayed months and the day after the last one
+fn displayed_range(config: &Config) -> (NaiveDate, NaiveDate) {
+    (displayed_month(config, 0), displayed_month(config, config.num_months))
+}
+
+// The week rows of a month grid as (ISO week number, days), with None for days outside the month.
+fn month_weeks(month_start: NaiveDate, monday_first: bool) -> Vec<(u32, [Option<NaiveDate>; 7])> {
+    let days_in_month = days_in_month(month_start.year(), month_start.month()) as i32;
+    (0..weeks_in_month(month_start, monday_first)).map(|week| {
+        let start_day = get_week_start_day(month_start, week, monday_first);
+        let mut days = [None; 7];
+        for (day_offset, slot) in days.iter_mut().enumerate() {
+            let day = start_day + day_offset as i32;
+            if day > 0 && day <= days_in_month {
+                *slot = NaiveDate::from_ymd_opt(month_start.year(), month_start.month(), day as u32);
             }
-            println!();
         }
-    }
-    if max_weeks < 6 { println!(); }
+        let week_date = month_start + Duration::days((start_day - 1).max(0) as i64);
+        (week_date.iso_week().week(), days)
+    }).collect()
 }
 
 fn get_week_start_day(month_start: NaiveDate, week_num: usize, monday_first: bool) -> i32 {
@@ -385,84 +918,104 @@ fn get_week_start_day(month_start: NaiveDate, week_num: usize, monday_first: boo
     (week_num * 7) as i32 - offset as i32 + 1
 }
 
-fn print_weekday_header(config: &Config) {
-    if config.show_week_numbers {
-        if config.monday_first { print!("\x1b[34mWk\x1b[0m Mo Tu We Th Fr \x1b[31mSa Su\x1b[0m"); }
-        else { print!("\x1b[34mWk\x1b[0m \x1b[31mSu\x1b[0m Mo Tu We Th Fr \x1b[31mSa\x1b[0m"); }
-    } else {
-        if config.monday_first { print!("Mo Tu We Th Fr \x1b[31mSa Su\x1b[0m"); }
-        else { print!("\x1b[31mSu\x1b[0m Mo Tu We Th Fr \x1b[31mSa\x1b[0m"); }
+// Events of one day; they are sorted by priority within a day, so the first one styles the day
+fn events_on(events: &[Event], date: NaiveDate) -> &[Event] {
+    let first_idx = events.partition_point(|e| e.date < date);
+    let count = events[first_idx..].partition_point(|e| e.date == date);
+    &events[first_idx..first_idx + count]
+}
+
+fn format_month_row(out: &mut String, config: &Config, events: &[Event], dates: &[NaiveDate], ansi: bool) {
+    let style = |code: &'static str| if ansi { code } else { "" };
+    let calendar_width = if config.show_week_numbers { 24 } else { 21 };
+
+    let titles: Vec<String> = dates.iter().map(|date| {
+        let month_name_str = format!("{} {}", month_name(date.month()), date.year());
+        let padding = (calendar_width - month_name_str.len()) / 2;
+        let trailing = calendar_width.saturating_sub(padding).saturating_sub(month_name_str.len());
+        format!("{}{}{}{}{}", " ".repeat(padding), style("\x1b[1m"), month_name_str, style("\x1b[0m"), " ".repeat(trailing))
+    }).collect();
+    out.push_str(&format!("{}\n", titles.join("    ")));
+
+    let mut header: Vec<String> = Vec::new();
+    if config.show_week_numbers { header.push(format!("{}Wk{}", style("\x1b[34m"), style("\x1b[0m"))); }
+    for name in render::weekday_abbreviations(config.monday_first) {
+        let is_weekend = name == "Sa" || name == "Su";
+        header.push(if is_weekend { format!("{}{}{}", style("\x1b[31m"), name, style("\x1b[0m")) } else { name.to_string() });
     }
+    out.push_str(&format!("{}\n", vec![header.join(" "); dates.len()].join("     ")));
+
+    let weeks: Vec<_> = dates.iter().map(|date| month_weeks(*date, config.monday_first)).collect();
+    let max_weeks = weeks.iter().map(Vec::len).max().unwrap_or(6);
+    for week in 0..max_weeks {
+        let rows: Vec<String> = weeks.iter().map(|month| match month.get(week) {
+            Some((week_number, days)) => format_week_row(*week_number, days, config, events, ansi),
+            None => " ".repeat(calendar_width),
+        }).collect();
+        out.push_str(&format!("{}\n", rows.join("    ")));
+    }
+    if max_weeks < 6 { out.push('\n'); }
 }
 
-fn print_week_row(month_start: NaiveDate, week_num: usize, config: &Config, events: &[Event]) {
-    let days_in_month = days_in_month(month_start.year(), month_start.month());
-    let start_day = get_week_start_day(month_start, week_num, config.monday_first);
+fn format_week_row(week_number: u32, days: &[Option<NaiveDate>; 7], config: &Config, events: &[Event], ansi: bool) -> String {
     let today = chrono::Local::now().naive_local().date();
+    let mut line = String::new();
 
     if config.show_week_numbers {
-        if start_day <= days_in_month as i32 && start_day + 6 >= 1 {
-            let week_date = month_start + Duration::days((start_day - 1).max(0) as i64);
-            print!("\x1b[34m{:2}\x1b[0m ", week_date.iso_week().week());
-        } else {
-            print!("   ");
-        }
-    }
-
-    for day_offset in 0..7 {
-        let day = start_day + day_offset;
-        if day > 0 && day <= days_in_month as i32 {
-            if let Some(current_date) = NaiveDate::from_ymd_opt(month_start.year(), month_start.month(), day as u32) {
-                let event_for_day = events
-                .binary_search_by_key(&current_date, |e| e.date)
-                .ok()
-                .map(|idx| &events[idx]);
-
-                let is_today = current_date == today;
-                let is_weekend = matches!(current_date.weekday(), Weekday::Sat | Weekday::Sun);
-
-                let mut styles = [""; 4];
-                let mut style_len = 0;
-
-                if is_today {
-                    styles[style_len] = event_for_day.and_then(|e| e.bg_color.as_ref()).and_then(|c| get_ansi_color_code(c, false)).unwrap_or("\x1b[43m");
-                    style_len += 1;
-                    styles[style_len] = "\x1b[1m"; // Bold
-                    style_len += 1;
-                    styles[style_len] = "\x1b[30m"; // Black text
-                    style_len += 1;
-                } else if is_weekend {
-                    styles[style_len] = "\x1b[31m"; // Red for weekends
-                    style_len += 1;
-                    if event_for_day.is_some() {
-                        styles[style_len] = "\x1b[1m";
-                        style_len += 1;
-                    }
-                } else if let Some(event) = event_for_day {
-                    if let Some(fg) = event.fg_color.as_ref().and_then(|c| get_ansi_color_code(c, true)) {
-                        styles[style_len] = fg;
-                        style_len += 1;
-                    }
-                    if let Some(bg) = event.bg_color.as_ref().and_then(|c| get_ansi_color_code(c, false)) {
-                        styles[style_len] = bg;
-                        style_len += 1;
-                    } else {
-                        styles[style_len] = "\x1b[7m"; // Inverse video fallback
-                        style_len += 1;
-                    }
-                    styles[style_len] = "\x1b[1m"; // Bold for events
-                    style_len += 1;
-                }
+        if ansi { line.push_str(&format!("\x1b[34m{:2}\x1b[0m ", week_number)); } else { line.push_str(&format!("{:2} ", week_number)); }
+    }
+
+    for day in days {
+        let Some(current_date) = *day else {
+            line.push_str("   ");
+            continue;
+        };
+        if !ansi {
+            line.push_str(&format!("{:2} ", current_date.day()));
+            continue;
+        }
+        let event_for_day = events_on(events, current_date).first();
 
-                for i in 0..style_len { print!("{}", styles[i]); }
-                print!("{:2}\x1b[0m ", day);
+        let is_today = current_date == today;
+        let is_weekend = matches!(current_date.weekday(), Weekday::Sat | Weekday::Sun);
+
+        let mut styles = [""; 4];
+        let mut style_len = 0;
+
+        if is_today {
+            styles[style_len] = event_for_day.and_then(|e| e.bg_color.as_ref()).and_then(|c| get_ansi_color_code(c, false)).unwrap_or("\x1b[43m");
+            style_len += 1;
+            styles[style_len] = "\x1b[1m"; // Bold
+            style_len += 1;
+            styles[style_len] = "\x1b[30m"; // Black text
+            style_len += 1;
+        } else if is_weekend {
+            styles[style_len] = "\x1b[31m"; // Red for weekends
+            style_len += 1;
+            if event_for_day.is_some() {
+                styles[style_len] = "\x1b[1m";
+                style_len += 1;
+            }
+        } else if let Some(event) = event_for_day {
+            if let Some(fg) = event.fg_color.as_ref().and_then(|c| get_ansi_color_code(c, true)) {
+                styles[style_len] = fg;
+                style_len += 1;
+            }
+            if let Some(bg) = event.bg_color.as_ref().and_then(|c| get_ansi_color_code(c, false)) {
+                styles[style_len] = bg;
+                style_len += 1;
             } else {
-                print!("   ");
+                styles[style_len] = "\x1b[7m"; // Inverse video fallback
+                style_len += 1;
             }
-        } else {
-            print!("   ");
+            styles[style_len] = "\x1b[1m"; // Bold for events
+            style_len += 1;
         }
+
+        for style in &styles[..style_len] { line.push_str(style); }
+        line.push_str(&format!("{:2}\x1b[0m ", current_date.day()));
     }
+    line
 }
 
 fn get_ordinal_suffix(n: i32) -> &'static str {
@@ -474,54 +1027,57 @@ fn get_ordinal_suffix(n: i32) -> &'static str {
     }
 }
 
-fn display_events_list(config: &Config, events: &[Event]) {
-    let today = chrono::Local::now().naive_local().date();
+// Years since the original date for events of counting categories (birthdays, anniversaries...)
+fn anniversary_number(event: &Event) -> Option<i32> {
+    let anniversary_num = event.date.year() - event.original_year?;
+    event.count_format.as_ref()?;
+    (anniversary_num > 0).then_some(anniversary_num)
+}
 
-    let start_date = NaiveDate::from_ymd_opt(config.start_year, config.start_month, 1).unwrap_or(today);
-    let total_months_from_epoch = config.start_year as i64 * 12 + config.start_month as i64 + config.num_months as i64;
-    let end_year = ((total_months_from_epoch - 1) / 12) as i32;
-    let end_month = ((total_months_from_epoch - 1) % 12 + 1) as u32;
-    let end_date = NaiveDate::from_ymd_opt(end_year, end_month, 1).unwrap_or(today);
+fn format_anniversary_count(event: &Event) -> Option<String> {
+    let anniversary_num = anniversary_number(event)?;
+    let count_format = event.count_format.as_ref()?;
+    let nth = format!("{}{}", anniversary_num, get_ordinal_suffix(anniversary_num));
+    Some(count_format.replace("{nth}", &nth).replace("{n}", &anniversary_num.to_string()))
+}
 
-    let filtered_events: Vec<&Event> = events.iter().filter(|e| e.date >= start_date && e.date < end_date).collect();
-    if filtered_events.is_empty() { return; }
+// Events inside the window of --from/--to or --next/--past/--count, or else the displayed months,
+// with the first day and the day after the last one
+fn events_in_display_range<'a>(config: &Config, events: &'a [Event]) -> (Vec<&'a Event>, NaiveDate, NaiveDate) {
+    let (start_date, end_date) = match config.window {
+        Some((from, to)) => (from, to.succ_opt().unwrap_or(to)),
+        None => displayed_range(config),
+    };
+    let filtered_events = events.iter().filter(|e| e.date >= start_date && e.date < end_date).collect();
+    (filtered_events, start_date, end_date)
+}
 
-    println!("\n\x1b[1mEvents:\x1b[0m");
-    println!("{}", "-".repeat(80));
+fn format_events_list(config: &Config, events: &[Event], ansi: bool) -> String {
+    let today = chrono::Local::now().naive_local().date();
+    let (filtered_events, _, _) = events_in_display_range(config, events);
+    if filtered_events.is_empty() { return String::new(); }
 
+    let heading = if ansi { "\x1b[1mEvents:\x1b[0m" } else { "Events:" };
+    let mut out = format!("\n{}\n{}\n", heading, "-".repeat(80));
     for event in filtered_events {
-        let fg_code = event.fg_color.as_ref().and_then(|c| get_ansi_color_code(c, true)).unwrap_or("");
-        let bg_code = event.bg_color.as_ref().and_then(|c| get_ansi_color_code(c, false)).unwrap_or("");
-
-        let mut full_description = event.description.clone();
-        if let Some(original_year) = event.original_year {
-            if let Some(cat) = &event.category {
-                let (label, qualifies) = match cat.as_str() {
-                    "bday" => ("Birthday", true),
-                    "anni" => ("Anniversary", true),
-                    _ => ("", false),
-                };
-                if qualifies {
-                    let anniversary_num = event.date.year() - original_year;
-                    if anniversary_num > 0 {
-                        full_description.push_str(&format!(" ({}{} {})", anniversary_num, get_ordinal_suffix(anniversary_num), label));
-                    }
-                }
-            }
-        }
-
-        let days_diff = event.date.signed_duration_since(today).num_days();
-        let relative_days_label = if days_diff == 0 {
-            " \x1b[1m\x1b[33m(Today 📌)\x1b[0m".to_string()
-        } else if days_diff > 0 {
-            format!(" \x1b[32m(In \x1b[1m{}\x1b[0m\x1b[32m days)\x1b[0m", days_diff)
-        } else {
-            format!(" \x1b[34m(\x1b[1m{}\x1b[0m\x1b[34m days ago)\x1b[0m", days_diff.abs())
-        };
-        full_description.push_str(&relative_days_label);
+        out.push_str(&format!("{}\n", format_event_entry(event, today, ansi)));
+    }
+    out
+}
 
-        println!("{}{}{}\x1b[0m - {}", bg_code, fg_code, event.date.format("%a, %d %b %Y"), full_description);
+// One entry of the events list: the date in the event's colors (with `ansi`), label, description,
+// year count and how far the date is from today
+fn format_event_entry(event: &Event, today: NaiveDate, ansi: bool) -> String {
+    let label = event.label.as_ref().map(|l| format!("[{}] ", l)).unwrap_or_default();
+    let description = format!("{}{}", render::full_description(event), render::relative_days_label(event.date, today, ansi));
+    let date = event.date.format("%a, %d %b %Y");
+    if !ansi {
+        return format!("{} - {}{}", date, label, description);
     }
+
+    let fg_code = event.fg_color.as_ref().and_then(|c| get_ansi_color_code(c, true)).unwrap_or("");
+    let bg_code = event.bg_color.as_ref().and_then(|c| get_ansi_color_code(c, false)).unwrap_or("");
+    format!("{}{}{}\x1b[0m - {}{}", bg_code, fg_code, date, label, description)
 }
 
 fn get_ansi_color_code(color_name: &str, is_fg: bool) -> Option<&'static str> {
@@ -538,6 +1094,48 @@ fn get_ansi_color_code(color_name: &str, is_fg: bool) -> Option<&'static str> {
     }
 }
 
+// CSS colors for the color names, close to the usual terminal palette
+fn css_color(color_name: &str) -> Option<&'static str> {
+    match color_name.to_lowercase().as_str() {
+        "black" => Some("#000000"),
+        "red" => Some("#cd3131"),
+        "green" => Some("#0dbc79"),
+        "yellow" => Some("#e5e510"),
+        "blue" => Some("#2472c8"),
+        "magenta" => Some("#bc3fbc"),
+        "cyan" => Some("#11a8cd"),
+        "white" => Some("#e5e5e5"),
+        _ => None,
+    }
+}
+
+// Month from its English name or an abbreviation of at least three letters
+fn month_from_name(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    if name.len() < 3 { return None; }
+    (1..=12).find(|m| month_name(*m).to_lowercase().starts_with(&name))
+}
+
+// Three letter names as remind(1) and calendar(1) write them
+fn short_month(month: u32) -> &'static str {
+    &month_name(month)[..3]
+}
+
+fn short_weekday(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Mon", Weekday::Tue => "Tue", Weekday::Wed => "Wed", Weekday::Thu => "Thu",
+        Weekday::Fri => "Fri", Weekday::Sat => "Sat", Weekday::Sun => "Sun",
+    }
+}
+
+fn weekday_from_name(name: &str) -> Option<Weekday> {
+    let name = name.to_lowercase();
+    if name.len() < 3 { return None; }
+    let weekdays = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri, Weekday::Sat, Weekday::Sun];
+    let names = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+    names.iter().position(|n| n.starts_with(&name)).map(|idx| weekdays[idx])
+}
+
 fn month_name(month: u32) -> &'static str {
     match month {
         1 => "January", 2 => "February", 3 => "March", 4 => "April", 5 => "May", 6 => "June",
@@ -558,6 +1156,7 @@ fn days_in_month(year: i32, month: u32) -> u32 {
     next_month_start.signed_duration_since(current_month_start).num_days() as u32
 }
 
+#[allow(clippy::manual_div_ceil)]
 fn weeks_in_month(month_start: NaiveDate, monday_first: bool) -> usize {
     let offset = if monday_first {
         month_start.weekday().num_days_from_monday()
@@ -567,3 +1166,81 @@ fn weeks_in_month(month_start: NaiveDate, monday_first: bool) -> usize {
     let days = days_in_month(month_start.year(), month_start.month());
     ((offset + days + 6) / 7) as usize
 }
+
+// FNV-1a keeps UIDs and line ids stable between runs and versions, unlike std's randomly seeded hasher
+fn fnv1a_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules_with(content: &str, packs: &[&str]) -> RuleSet {
+        let path = std::env::temp_dir().join(format!("ecal-test-{}-{}.txt", std::process::id(), fnv1a_hash(content)));
+        fs::write(&path, content).unwrap();
+        let config = Config { holidays: packs.iter().map(|p| p.to_string()).collect(), ..Config::default() };
+        let rules = load_rules(path.to_str().unwrap(), &config);
+        fs::remove_file(&path).unwrap();
+        rules
+    }
+
+    #[test]
+    fn user_rule_overrides_pack_rule_of_same_category_only() {
+        let rules = rules_with("1/1 ;[ie] New Year's Day\n", &["de", "ie"]);
+        let new_years: Vec<_> = rules.event_lines.iter()
+            .filter(|(_, _, l)| l.rule_text == "1/1")
+            .map(|(source, _, l)| (source.as_str(), l.category.as_deref()))
+            .collect();
+        assert_eq!(new_years.len(), 2);
+        assert!(new_years.contains(&("holidays/de.txt", Some("de"))));
+        assert!(new_years.iter().any(|(source, category)| !source.starts_with("holidays/") && *category == Some("ie")));
+    }
+
+    #[test]
+    fn region_and_country_packs_are_read_once() {
+        let rules = rules_with("", &["de", "de-by"]);
+        let new_years = rules.event_lines.iter().filter(|(_, _, l)| l.description.contains("Neujahr")).count();
+        assert_eq!(new_years, 1);
+        let sources: HashSet<&str> = rules.event_lines.iter().map(|(source, _, _)| source.as_str()).collect();
+        assert_eq!(sources, HashSet::from(["holidays/de.txt", "holidays/de-by.txt"]));
+    }
+
+    #[test]
+    fn st_brigids_day_is_first_monday_of_february_or_friday_first() {
+        let rules = rules_with("", &["ie"]);
+        let dates: Vec<NaiveDate> = (2026..=2030).map(|year| {
+            let days: Vec<NaiveDate> = rules.event_lines.iter()
+                .filter(|(_, _, l)| l.description.contains("Brigid"))
+                .filter_map(|(_, _, l)| l.rule.date_in_year(year))
+                .collect();
+            assert_eq!(days.len(), 1, "{} has {:?}", year, days);
+            days[0]
+        }).collect();
+        let expected = ["2026-02-02", "2027-02-01", "2028-02-07", "2029-02-05", "2030-02-01"];
+        assert_eq!(dates, expected.map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap()));
+    }
+
+    fn without_escape_codes(text: &str) -> String {
+        let mut out = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' { chars.by_ref().find(|c| *c == 'm'); } else { out.push(c); }
+        }
+        out
+    }
+
+    #[test]
+    fn plain_output_is_the_terminal_output_without_escape_codes() {
+        let content = "1/1 ;[red] New Year\nE ;[,yellow,blue] Easter\n11/4#4 ;Thanksgiving\n12-11-1990 ;[bday] Sam\n";
+        let path = std::env::temp_dir().join(format!("ecal-test-{}-{}.txt", std::process::id(), fnv1a_hash(content)));
+        fs::write(&path, content).unwrap();
+        for (num_months, num_columns, monday_first, show_week_numbers) in [(1, 3, true, true), (5, 2, false, true), (12, 4, true, false)] {
+            let config = Config { num_months, num_columns, monday_first, show_week_numbers, start_month: 11, start_year: 2026, ..Config::default() };
+            let events = load_events(path.to_str().unwrap(), &config);
+            let terminal = format_calendars(&config, &events, true) + &format_events_list(&config, &events, true);
+            assert_eq!(markdown::render_plain(&config, &events), without_escape_codes(&terminal));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
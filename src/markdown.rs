@@ -0,0 +1,67 @@
+// Markdown and plain-text rendering of the calendar and events list, for wikis and notes.
+// The plain text is the terminal output without any escape codes.
+
+use crate::render::{full_description, relative_days_label, weekday_abbreviations};
+use crate::{Config, Event, displayed_months, events_in_display_range, events_on, format_calendars, format_events_list, month_name, month_weeks};
+use chrono::Datelike;
+
+pub fn render_markdown(config: &Config, events: &[Event]) -> String {
+    let today = chrono::Local::now().naive_local().date();
+    let mut out = String::new();
+
+    if config.show_calendar {
+        for month_start in displayed_months(config) {
+            out.push_str(&format!("### {} {}\n\n", month_name(month_start.month()), month_start.year()));
+            let mut header = if config.show_week_numbers { vec!["Wk"] } else { Vec::new() };
+            header.extend(weekday_abbreviations(config.monday_first));
+            out.push_str(&format!("| {} |\n|{}\n", header.join(" | "), "---:|".repeat(header.len())));
+
+            for (week_number, days) in month_weeks(month_start, config.monday_first) {
+                let mut cells = if config.show_week_numbers { vec![week_number.to_string()] } else { Vec::new() };
+                for day in days {
+                    cells.push(match day {
+                        Some(date) if date == today => format!("**[{}]**", date.day()),
+                        Some(date) if !events_on(events, date).is_empty() => format!("**{}**", date.day()),
+                        Some(date) => date.day().to_string(),
+                        None => String::new(),
+                    });
+                }
+                out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            }
+            out.push('\n');
+        }
+    }
+
+    let (filtered_events, _, _) = events_in_display_range(config, events);
+    if config.show_events && !filtered_events.is_empty() {
+        out.push_str("### Events\n\n");
+        for event in filtered_events {
+            let label = event.label.as_ref().map(|l| format!("[{}] ", l)).unwrap_or_default();
+            out.push_str(&format!(
+                "- **{}** – {}{}{}\n",
+                event.date.format("%a, %d %b %Y"),
+                escape(&label),
+                escape(&full_description(event)),
+                relative_days_label(event.date, today, false),
+            ));
+        }
+    }
+    out
+}
+
+// The terminal output without any escape codes
+pub fn render_plain(config: &Config, events: &[Event]) -> String {
+    let mut out = String::new();
+    if config.show_calendar { out.push_str(&format_calendars(config, events, false)); }
+    if config.show_events { out.push_str(&format_events_list(config, events, false)); }
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::new();
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '[' | ']' | '`' | '|' | '<' | '>' | '#') { out.push('\\'); }
+        out.push(c);
+    }
+    out
+}
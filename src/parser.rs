@@ -0,0 +1,484 @@
+// Parser for the events file format. Every line parses to either a `Line` or a
+// diagnostic; nothing in here panics on user input.
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::fmt;
+
+pub const COLOR_NAMES: &[&str] = &["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rule {
+    Fixed(NaiveDate),                                         // DD-MM-YYYY, MM/DD/YYYY, YYYY-MM-DD
+    Easter(i64),                                              // E, E+N, E-N
+    NthWeekday { month: u32, weekday: Weekday, n: u32 },      // MM/DOW#N, N = 5 is the last one
+    Annual { month: u32, day: u32 },                          // MM/DD, MM/DD?
+    InYear { month: u32, day: u32, year: i32 },               // MM/DD?YYYY
+    IfWeekday { month: u32, day: u32, weekday: Weekday, offset: i64 }, // MM/DD?D+N, MM/DD?D-N
+}
+
+impl Rule {
+    pub fn date_in_year(&self, year: i32) -> Option<NaiveDate> {
+        match *self {
+            Rule::Fixed(date) => (date.year() == year).then_some(date),
+            Rule::Easter(offset) => calculate_easter_date(year)?.checked_add_signed(Duration::try_days(offset)?),
+            Rule::NthWeekday { month, weekday, n } => find_nth_dow(year, month, weekday, n),
+            Rule::Annual { month, day } => NaiveDate::from_ymd_opt(year, month, day),
+            Rule::InYear { month, day, year: rule_year } => {
+                if year == rule_year { NaiveDate::from_ymd_opt(year, month, day) } else { None }
+            }
+            Rule::IfWeekday { month, day, weekday, offset } => {
+                let target_date = NaiveDate::from_ymd_opt(year, month, day)?;
+                if target_date.weekday() != weekday { return None; }
+                // Offsets of any size are accepted; ones beyond the supported dates give no date
+                target_date.checked_add_signed(Duration::try_days(offset)?)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    InvalidRule(String),
+    ImpossibleDate { month: u32, day: u32 },
+    UnknownColor(String),
+    UnterminatedMeta,
+    InvalidDirective(String),
+    Unreadable(String),
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::InvalidRule(reason) => write!(f, "invalid rule: {}", reason),
+            ErrorKind::ImpossibleDate { month, day } => write!(f, "impossible date: month {} has no day {}", month, day),
+            ErrorKind::UnknownColor(name) => write!(f, "unknown color '{}' (expected one of: {})", name, COLOR_NAMES.join(", ")),
+            ErrorKind::UnterminatedMeta => write!(f, "unterminated '[' meta block"),
+            ErrorKind::InvalidDirective(reason) => write!(f, "invalid directive: {}", reason),
+            ErrorKind::Unreadable(reason) => write!(f, "cannot read file: {}", reason),
+        }
+    }
+}
+
+// A problem found on one line; `column` is a 1-based character position
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineError {
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub kind: ErrorKind,
+}
+
+impl ParseError {
+    // `file:line:column`, or just the file for problems with the file as a whole
+    pub fn location(&self) -> String {
+        if self.line == 0 { self.file.clone() } else { format!("{}:{}:{}", self.file, self.line, self.column) }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.location(), self.kind)
+    }
+}
+
+// Defaults declared with `@category <name> fg=.. bg=.. label=".." priority=N count=".."`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CategoryDef {
+    pub fg_color: Option<String>,
+    pub bg_color: Option<String>,
+    pub label: Option<String>,
+    pub priority: Option<i32>,
+    pub count_format: Option<String>, // Makes full dates recur yearly; {n} and {nth} are replaced by the year count
+}
+
+impl CategoryDef {
+    // Settings present in `other` replace ours, the rest are kept
+    pub fn merge(&mut self, other: &CategoryDef) {
+        if other.fg_color.is_some() { self.fg_color = other.fg_color.clone(); }
+        if other.bg_color.is_some() { self.bg_color = other.bg_color.clone(); }
+        if other.label.is_some() { self.label = other.label.clone(); }
+        if other.priority.is_some() { self.priority = other.priority; }
+        if other.count_format.is_some() { self.count_format = other.count_format.clone(); }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventLine {
+    pub rule: Rule,
+    pub rule_text: String,
+    pub category: Option<String>,
+    pub fg_color: Option<String>,
+    pub bg_color: Option<String>,
+    pub description: String,
+}
+
+impl EventLine {
+    // Writes the line back in the events file format, rule column aligned like events.txt
+    pub fn to_line(&self) -> String {
+        format!("{:<9} ;{}{}", self.rule_text, self.meta_block(), self.description).trim_end().to_string()
+    }
+
+    // What goes between ';' and the description: "[category, fg, bg] " with unused trailing fields
+    // left out, or a single space
+    pub fn meta_block(&self) -> String {
+        let meta_parts = [self.category.as_deref(), self.fg_color.as_deref(), self.bg_color.as_deref()];
+        let used = meta_parts.iter().rposition(|p| p.is_some()).map_or(0, |i| i + 1);
+        if used > 0 || self.description.starts_with('[') {
+            format!("[{}] ", meta_parts[..used].iter().map(|p| p.unwrap_or("")).collect::<Vec<_>>().join(", "))
+        } else {
+            " ".to_string()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    Blank, // Empty line or comment
+    Category { name: String, def: CategoryDef },
+    Event(EventLine),
+    Invalid,
+}
+
+// Parses a whole source and returns its meaningful lines with 1-based line numbers
+pub fn parse_source(file: &str, content: &str) -> (Vec<(usize, Line)>, Vec<ParseError>) {
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, text) in content.lines().enumerate() {
+        let (line, mut line_errors) = parse_line(text);
+        line_errors.sort_by_key(|e| e.column);
+        errors.extend(line_errors.into_iter().map(|e| ParseError { file: file.to_string(), line: idx + 1, column: e.column, kind: e.kind }));
+        if !matches!(line, Line::Blank | Line::Invalid) {
+            lines.push((idx + 1, line));
+        }
+    }
+    (lines, errors)
+}
+
+pub fn parse_line(text: &str) -> (Line, Vec<LineError>) {
+    let trimmed_line = text.trim();
+    if trimmed_line.is_empty() || trimmed_line.starts_with('#') {
+        return (Line::Blank, Vec::new());
+    }
+    if trimmed_line.starts_with('@') {
+        return parse_directive(text, trimmed_line);
+    }
+
+    let mut errors = Vec::new();
+    let (rule_text, rest) = match trimmed_line.split_once(';') {
+        Some((rule, rest)) => (rule.trim(), Some(rest.trim())),
+        None => match trimmed_line.split_once(char::is_whitespace) {
+            Some((rule, desc)) => (rule, Some(desc.trim())),
+            None => (trimmed_line, None),
+        },
+    };
+
+    let mut category: Option<String> = None;
+    let mut fg_color: Option<String> = None;
+    let mut bg_color: Option<String> = None;
+
+    let description = match rest {
+        Some(rest) if rest.starts_with('[') => match rest.find(']') {
+            Some(end_bracket) => {
+                let meta_block = &rest[1..end_bracket];
+                let meta_parts: Vec<&str> = meta_block.split(',').map(|s| s.trim()).collect();
+                if let Some(cat) = meta_parts.first() && !cat.is_empty() { category = Some(cat.to_string()); }
+                for (idx, slot) in [(1, &mut fg_color), (2, &mut bg_color)] {
+                    if let Some(color) = meta_parts.get(idx) && !color.is_empty() {
+                        if !is_known_color(color) {
+                            errors.push(LineError { column: column_of(text, color), kind: ErrorKind::UnknownColor(color.to_string()) });
+                        }
+                        *slot = Some(color.to_string());
+                    }
+                }
+                if let Some(extra) = meta_parts.get(3) {
+                    errors.push(LineError { column: column_of(text, extra), kind: ErrorKind::InvalidRule("meta block takes at most [category, fg_color, bg_color]".to_string()) });
+                }
+                rest[end_bracket + 1..].trim().to_string()
+            }
+            None => {
+                errors.push(LineError { column: column_of(text, rest), kind: ErrorKind::UnterminatedMeta });
+                rest.to_string()
+            }
+        },
+        Some(rest) => rest.to_string(),
+        None => String::new(),
+    };
+
+    match parse_rule(rule_text) {
+        Ok(rule) => {
+            let event = EventLine { rule, rule_text: rule_text.to_string(), category, fg_color, bg_color, description };
+            (Line::Event(event), errors)
+        }
+        Err(e) => {
+            errors.insert(0, LineError { column: column_of(text, rule_text) + e.column - 1, kind: e.kind });
+            (Line::Invalid, errors)
+        }
+    }
+}
+
+fn parse_directive(text: &str, trimmed_line: &str) -> (Line, Vec<LineError>) {
+    let mut errors = Vec::new();
+    let directive = trimmed_line.strip_prefix("@category").filter(|r| r.is_empty() || r.starts_with(char::is_whitespace));
+    let Some(rest) = directive else {
+        let name = trimmed_line.split_whitespace().next().unwrap_or(trimmed_line);
+        let kind = ErrorKind::InvalidDirective(format!("unknown directive '{}'", name));
+        return (Line::Invalid, vec![LineError { column: column_of(text, trimmed_line), kind }]);
+    };
+
+    let (args, unterminated) = split_directive_args(rest);
+    if let Some(quote_pos) = unterminated {
+        errors.push(LineError { column: column_of(text, &rest[quote_pos..]), kind: ErrorKind::InvalidDirective("unterminated quote".to_string()) });
+    }
+    let Some(((_, name), settings)) = args.split_first() else {
+        errors.push(LineError { column: column_of(text, trimmed_line), kind: ErrorKind::InvalidDirective("@category needs a name".to_string()) });
+        return (Line::Invalid, errors);
+    };
+
+    let mut def = CategoryDef::default();
+    for (pos, setting) in settings {
+        let column = column_of(text, &rest[*pos..]);
+        match setting.split_once('=') {
+            Some((key @ ("fg" | "bg"), val)) => {
+                if !is_known_color(val) {
+                    errors.push(LineError { column, kind: ErrorKind::UnknownColor(val.to_string()) });
+                }
+                if key == "fg" { def.fg_color = Some(val.to_string()); } else { def.bg_color = Some(val.to_string()); }
+            }
+            Some(("label", val)) => def.label = Some(val.to_string()),
+            Some(("count", val)) => def.count_format = Some(val.to_string()),
+            Some(("priority", val)) => match val.parse() {
+                Ok(p) => def.priority = Some(p),
+                Err(_) => errors.push(LineError { column, kind: ErrorKind::InvalidDirective(format!("invalid priority '{}'", val)) }),
+            },
+            _ => errors.push(LineError { column, kind: ErrorKind::InvalidDirective(format!("unknown setting '{}'", setting)) }),
+        }
+    }
+    (Line::Category { name: name.clone(), def }, errors)
+}
+
+// Splits directive arguments on whitespace, keeping "quoted values" together. Each argument
+// comes with its byte position; the position of an unterminated quote is returned separately.
+fn split_directive_args(s: &str) -> (Vec<(usize, String)>, Option<usize>) {
+    let mut args = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    let mut quote_start: Option<usize> = None;
+    for (pos, c) in s.char_indices() {
+        match c {
+            '"' => {
+                quote_start = if quote_start.is_some() { None } else { Some(pos) };
+                current.get_or_insert_with(|| (pos, String::new()));
+            }
+            c if c.is_whitespace() && quote_start.is_none() => args.extend(current.take()),
+            c => current.get_or_insert_with(|| (pos, String::new())).1.push(c),
+        }
+    }
+    args.extend(current);
+    (args, quote_start)
+}
+
+#[derive(Debug)]
+pub struct RuleError {
+    pub column: usize, // 1-based character position within the rule
+    pub kind: ErrorKind,
+}
+
+impl RuleError {
+    fn new(rule: &str, part: &str, reason: String) -> Self {
+        RuleError { column: column_of(rule, part), kind: ErrorKind::InvalidRule(reason) }
+    }
+}
+
+pub fn parse_rule(rule: &str) -> Result<Rule, RuleError> {
+    let rule = rule.trim();
+    if let Some(date) = parse_fixed_date_rule(rule) {
+        return Ok(Rule::Fixed(date));
+    }
+    if let Some((year_text, month_text, day_text)) = full_date_fields(rule) {
+        let month = parse_number(rule, month_text, "month", 1..=12)?;
+        let day = parse_number(rule, day_text, "day", 1..=31)?;
+        let year = parse_number(rule, year_text, "year", 0..=9999)? as i32;
+        return match NaiveDate::from_ymd_opt(year, month, day) {
+            Some(date) => Ok(Rule::Fixed(date)),
+            None => Err(RuleError { column: column_of(rule, day_text), kind: ErrorKind::ImpossibleDate { month, day } }),
+        };
+    }
+    if let Some(offset_text) = rule.strip_prefix('E') {
+        let offset = if offset_text.is_empty() { 0 } else { parse_offset(rule, offset_text)? };
+        return Ok(Rule::Easter(offset));
+    }
+    if let Some((date_part, n_text)) = rule.split_once('#') {
+        let (month_text, dow_text) = date_part.split_once('/')
+            .ok_or_else(|| RuleError::new(rule, date_part, format!("expected MM/DOW before '#', found '{}'", date_part)))?;
+        let month = parse_number(rule, month_text, "month", 1..=12)?;
+        let dow_num = parse_number(rule, dow_text, "day of week (0=Sun..6=Sat)", 0..=7)?;
+        let n = parse_number(rule, n_text, "occurrence", 1..=5)?;
+        return Ok(Rule::NthWeekday { month, weekday: weekday_from_sunday_index(dow_num % 7), n });
+    }
+    if let Some((date_part, condition_part)) = rule.split_once('?') {
+        let (month, day) = parse_month_day(rule, date_part)?;
+        if condition_part.is_empty() {
+            return Ok(Rule::Annual { month, day });
+        }
+        if condition_part.len() == 4 && condition_part.chars().all(|c| c.is_ascii_digit()) {
+            let year = parse_number(rule, condition_part, "year", 0..=9999)?;
+            return Ok(Rule::InYear { month, day, year: year as i32 });
+        }
+        let mut chars = condition_part.chars();
+        let dow_num = chars.next().and_then(|c| c.to_digit(10)).filter(|d| *d <= 6)
+            .ok_or_else(|| RuleError::new(rule, condition_part, format!("expected YYYY or D+N/D-N after '?' (D: 0=Sun..6=Sat), found '{}'", condition_part)))?;
+        let sign = match chars.next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => return Err(RuleError::new(rule, condition_part, format!("expected '+' or '-' after the day of week in '{}'", condition_part))),
+        };
+        let offset_text = chars.as_str();
+        let offset = parse_number(rule, offset_text, "offset", 0..=u32::MAX)? as i64 * sign;
+        return Ok(Rule::IfWeekday { month, day, weekday: weekday_from_sunday_index(dow_num), offset });
+    }
+    if rule.contains('/') {
+        let (month, day) = parse_month_day(rule, rule)?;
+        return Ok(Rule::Annual { month, day });
+    }
+    Err(RuleError::new(rule, rule, format!("unrecognized rule '{}'", rule)))
+}
+
+fn parse_month_day(rule: &str, part: &str) -> Result<(u32, u32), RuleError> {
+    let mut fields = part.split('/');
+    let (Some(month_text), Some(day_text), None) = (fields.next(), fields.next(), fields.next()) else {
+        return Err(RuleError::new(rule, part, format!("expected MM/DD, found '{}'", part)));
+    };
+    let month = parse_number(rule, month_text, "month", 1..=12)?;
+    let day = parse_number(rule, day_text, "day", 1..=31)?;
+    // 2000 is a leap year, so Feb 29 is accepted
+    if NaiveDate::from_ymd_opt(2000, month, day).is_none() {
+        return Err(RuleError { column: column_of(rule, day_text), kind: ErrorKind::ImpossibleDate { month, day } });
+    }
+    Ok((month, day))
+}
+
+fn parse_number(rule: &str, text: &str, what: &str, range: std::ops::RangeInclusive<u32>) -> Result<u32, RuleError> {
+    match text.parse::<u32>() {
+        Ok(n) if range.contains(&n) && text.chars().all(|c| c.is_ascii_digit()) => Ok(n),
+        _ => Err(RuleError::new(rule, text, format!("expected {} in {}..={}, found '{}'", what, range.start(), range.end(), text))),
+    }
+}
+
+fn parse_offset(rule: &str, text: &str) -> Result<i64, RuleError> {
+    text.parse::<i64>().map_err(|_| RuleError::new(rule, text, format!("expected an offset in days, found '{}'", text)))
+}
+
+// (year, month, day) of anything shaped like DD-MM-YYYY, MM/DD/YYYY or YYYY-MM-DD, so a day the
+// month doesn't have is reported as such rather than as an unknown rule
+fn full_date_fields(rule: &str) -> Option<(&str, &str, &str)> {
+    let digits = |text: &str, lengths: std::ops::RangeInclusive<usize>| lengths.contains(&text.len()) && text.chars().all(|c| c.is_ascii_digit());
+    let dashed: Vec<&str> = rule.split('-').collect();
+    let slashed: Vec<&str> = rule.split('/').collect();
+    match (dashed.as_slice(), slashed.as_slice()) {
+        ([day, month, year], _) if digits(day, 1..=2) && digits(month, 1..=2) && digits(year, 4..=4) => Some((year, month, day)),
+        ([year, month, day], _) if digits(year, 4..=4) && digits(month, 1..=2) && digits(day, 1..=2) => Some((year, month, day)),
+        (_, [month, day, year]) if digits(month, 1..=2) && digits(day, 1..=2) && digits(year, 4..=4) => Some((year, month, day)),
+        _ => None,
+    }
+}
+
+pub fn parse_fixed_date_rule(rule: &str) -> Option<NaiveDate> {
+    if let Ok(date) = NaiveDate::parse_from_str(rule, "%d-%m-%Y") { return Some(date); }
+    if let Ok(date) = NaiveDate::parse_from_str(rule, "%m/%d/%Y") { return Some(date); }
+    if let Ok(date) = NaiveDate::parse_from_str(rule, "%Y-%m-%d") { return Some(date); }
+    None
+}
+
+// Categories from other tools become the [category] of a line, so keep them to a simple
+// lowercase word and map the common birthday/anniversary names onto the built-in ones
+pub fn normalize_category(text: &str) -> Option<String> {
+    let category: String = text.trim().to_lowercase().chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' }).collect();
+    match category.trim_matches('-') {
+        "" => None,
+        "birthday" | "birthdays" => Some("bday".to_string()),
+        "anniversary" | "anniversaries" => Some("anni".to_string()),
+        other => Some(other.to_string()),
+    }
+}
+
+pub fn is_known_color(name: &str) -> bool {
+    COLOR_NAMES.contains(&name.to_lowercase().as_str())
+}
+
+// 1-based character column of `part` within `text`; `part` must be a subslice of `text`
+fn column_of(text: &str, part: &str) -> usize {
+    let offset = (part.as_ptr() as usize).saturating_sub(text.as_ptr() as usize).min(text.len());
+    text.char_indices().take_while(|(i, _)| *i < offset).count() + 1
+}
+
+fn weekday_from_sunday_index(dow_num: u32) -> Weekday {
+    match dow_num {
+        1 => Weekday::Mon, 2 => Weekday::Tue, 3 => Weekday::Wed, 4 => Weekday::Thu,
+        5 => Weekday::Fri, 6 => Weekday::Sat, _ => Weekday::Sun,
+    }
+}
+
+pub fn calculate_easter_date(year: i32) -> Option<NaiveDate> {
+    if year < 1583 { return None; }
+    let a = year % 19; let b = year / 100; let c = year % 100;
+    let d = b / 4; let e = b % 4; let f = (b + 8) / 25; let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30; let i = c / 4; let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7; let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31; let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+pub fn find_nth_dow(year: i32, month: u32, target_weekday: Weekday, n: u32) -> Option<NaiveDate> {
+    if n == 0 || n > 5 { return None; }
+    let mut current_date = NaiveDate::from_ymd_opt(year, month, 1)?;
+    while current_date.month() == month && current_date.weekday() != target_weekday {
+        current_date = current_date.succ_opt()?;
+    }
+    if current_date.month() == month {
+        current_date = current_date.checked_add_signed(Duration::weeks((n - 1) as i64))?;
+        if n == 5 && current_date.month() != month { current_date -= Duration::weeks(1); }
+        if current_date.month() == month { return Some(current_date); }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_kinds(content: &str) -> Vec<ErrorKind> {
+        parse_source("events.txt", content).1.into_iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn impossible_dates_are_reported_in_every_form() {
+        for (rule, month, day) in [("2/30", 2, 30), ("31-02-2020", 2, 31), ("2020-02-30", 2, 30), ("02/30/2020", 2, 30), ("29-02-2023", 2, 29), ("4/31/2026", 4, 31)] {
+            assert_eq!(error_kinds(&format!("{} ;Test", rule)), vec![ErrorKind::ImpossibleDate { month, day }], "{}", rule);
+        }
+    }
+
+    #[test]
+    fn offsets_of_any_size_are_accepted() {
+        assert_eq!(parse_rule("E+400").ok(), Some(Rule::Easter(400)));
+        assert_eq!(parse_rule("3/17?6+1000").ok().map(|r| matches!(r, Rule::IfWeekday { offset: 1000, .. })), Some(true));
+        for rule in [format!("E+{}", i64::MAX), format!("E{}", i64::MIN), format!("3/17?6+{}", u32::MAX), format!("3/17?6-{}", u32::MAX)] {
+            let rule = parse_rule(&rule).unwrap_or_else(|_| panic!("{} is rejected", rule));
+            for year in [i32::MIN, 0, 2026, i32::MAX] {
+                let _ = rule.date_in_year(year);
+            }
+        }
+        assert_eq!(parse_rule("E+400").unwrap().date_in_year(2026), NaiveDate::from_ymd_opt(2027, 5, 10));
+    }
+
+    #[test]
+    fn full_dates_still_parse() {
+        for (rule, date) in [("29-02-2024", "2024-02-29"), ("2024-02-29", "2024-02-29"), ("02/29/2024", "2024-02-29"), ("1-3-2026", "2026-03-01")] {
+            assert_eq!(parse_rule(rule).ok(), Some(Rule::Fixed(NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap())), "{}", rule);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+// Import and export of remind(1) files. On import, REM lines with fixed dates, Easter offsets
+// (EASTERDATE expressions), Nth weekdays of a month and the weekday conditions of our own export
+// become rules; advance notices (+N) are dropped since the events list already counts down to
+// every event. Anything else is listed as skipped. On export, every rule becomes a REM line.
+
+use crate::parser::{EventLine, Rule, normalize_category, parse_rule};
+use crate::{ImportReport, RuleSet, month_from_name, short_month, short_weekday, weekday_from_name};
+use chrono::{Datelike, NaiveDate, Weekday};
+
+pub fn import_remind(content: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut easter_vars: Vec<String> = Vec::new();
+
+    for (line_no, line) in joined_lines(content) {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with(['#', ';']) { continue; }
+        let (keyword, rest) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+
+        match keyword.to_uppercase().as_str() {
+            "REM" => match parse_rem(rest, &easter_vars) {
+                Ok(entry) => {
+                    if entry.rules.iter().all(|(_, rule)| matches!(rule, Rule::Fixed(_))) { report.one_off += 1; } else { report.recurring += 1; }
+                    for (rule_text, rule) in entry.rules {
+                        let line = EventLine { rule, rule_text, category: entry.category.clone(), fg_color: None, bg_color: None, description: entry.description.clone() };
+                        report.lines.push(line.to_line());
+                    }
+                }
+                Err(reason) => report.skipped.push(format!("line {}: '{}' ({})", line_no, trimmed, reason)),
+            },
+            // Variables holding Easter Sunday can be used in later REM lines
+            "SET" if rest.to_lowercase().contains("easterdate(") => {
+                if let Some(name) = rest.split_whitespace().next() {
+                    easter_vars.push(name.to_lowercase());
+                }
+            }
+            _ => report.skipped.push(format!("line {}: '{}' ({} is not imported)", line_no, trimmed, keyword.to_uppercase())),
+        }
+    }
+    report
+}
+
+struct RemEntry {
+    rules: Vec<(String, Rule)>,
+    category: Option<String>,
+    description: String,
+}
+
+// Lines ending in a backslash continue on the next line
+fn joined_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (idx, line) in content.lines().enumerate() {
+        let (start, mut text) = current.take().unwrap_or((idx + 1, String::new()));
+        match line.strip_suffix('\\') {
+            Some(part) => { text.push_str(part); current = Some((start, text)); }
+            None => { text.push_str(line); lines.push((start, text)); }
+        }
+    }
+    lines.extend(current);
+    lines
+}
+
+fn parse_rem(text: &str, easter_vars: &[String]) -> Result<RemEntry, String> {
+    let tokens = tokenize(text);
+    let mut weekdays: Vec<Weekday> = Vec::new();
+    let (mut month, mut day, mut year, mut back, mut easter_offset) = (None, None, None, None, None);
+    let mut category = None;
+    let mut body = None;
+    let mut satisfy_weekday = None;
+
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let (position, token) = (tokens[idx].0, tokens[idx].1.as_str());
+        let upper = token.to_uppercase();
+        idx += 1;
+        match upper.as_str() {
+            "MSG" | "MSF" | "CAL" => {
+                body = Some(text[position + token.len()..].to_string());
+                break;
+            }
+            // Only the weekday condition export_remind writes for MM/DD?D±N rules
+            "SATISFY" => {
+                satisfy_weekday = Some(tokens.get(idx).and_then(|(_, t)| satisfied_weekday(t)).ok_or("SATISFY reminders are not imported")?);
+                idx += 1;
+            }
+            "RUN" | "SPECIAL" | "PS" | "PSFILE" => return Err(format!("{} reminders are not imported", upper)),
+            "ONCE" => {}
+            "PRIORITY" | "DURATION" | "WARN" | "SCHED" | "INFO" => idx += 1,
+            "AT" => {
+                // The time and its own +N delta and *N repeat
+                idx += 1;
+                while tokens.get(idx).is_some_and(|(_, t)| t.starts_with(['+', '*'])) { idx += 1; }
+            }
+            "TAG" => {
+                category = tokens.get(idx).and_then(|(_, t)| normalize_category(t));
+                idx += 1;
+            }
+            "SKIP" | "BEFORE" | "AFTER" | "OMIT" | "UNTIL" | "THROUGH" | "FROM" | "SCANFROM" | "ADDOMIT" | "NOQUEUE" =>
+                return Err(format!("{} has no ecal equivalent", upper)),
+            _ if token.starts_with('[') => {
+                easter_offset = Some(easter_expression(token, easter_vars).ok_or_else(|| format!("unsupported expression {}", token))?);
+            }
+            _ if token.starts_with('+') => {} // Advance notice
+            _ if token.starts_with('-') => back = Some(token.trim_start_matches('-').parse::<u32>().map_err(|_| format!("unsupported back '{}'", token))?),
+            _ if token.starts_with('*') => return Err("repeating reminders (*N) are not imported".to_string()),
+            _ => {
+                if let Ok(n) = token.parse::<i32>() {
+                    match n {
+                        1..=31 if day.is_none() => day = Some(n as u32),
+                        1990..=9999 if year.is_none() => year = Some(n),
+                        _ => return Err(format!("unexpected number '{}'", token)),
+                    }
+                } else if let Some(m) = month_from_name(token) {
+                    month = Some(m);
+                } else if let Some(weekday) = weekday_from_name(token) {
+                    weekdays.push(weekday);
+                } else {
+                    return Err(format!("unsupported '{}'", token));
+                }
+            }
+        }
+    }
+
+    let mut body = body.ok_or("no MSG text")?;
+    // Birthdays and anniversaries count the years with an expression on the original year, as
+    // written by export_remind; they become full dates again. Without a TAG there is no counting
+    // category to count them, so they stay yearly MM/DD rules.
+    if let Some((birth_year, counted)) = counted_year(&body) {
+        if year.is_some() { return Err("unexpected year next to a counted year".to_string()); }
+        if category.is_some() { year = Some(birth_year); }
+        body = counted;
+    }
+    let description = Some(clean_body(&body)).filter(|d| !d.is_empty()).ok_or("no MSG text")?;
+    let rule_texts: Vec<String> = match (easter_offset, &weekdays[..], month, day, year, back) {
+        // "19 Mar SATISFY [wkdaynum(trigdate())==1]" is 3/19 when it falls on a Monday
+        (None, [], Some(month), Some(day), None, None) if let Some(weekday) = satisfy_weekday => vec![format!("{}/{}?{}+0", month, day, weekday)],
+        _ if satisfy_weekday.is_some() => return Err("SATISFY reminders are not imported".to_string()),
+        (Some(offset), [], None, None, None, None) => vec![if offset == 0 { "E".to_string() } else { format!("E{:+}", offset) }],
+        (None, [], Some(month), Some(day), Some(year), None) => {
+            let date = NaiveDate::from_ymd_opt(year, month, day).ok_or_else(|| format!("invalid date {}-{}-{}", year, month, day))?;
+            vec![date.format("%d-%m-%Y").to_string()]
+        }
+        (None, [], Some(month), Some(day), None, None) => vec![format!("{}/{}", month, day)],
+        // A day without a month repeats every month
+        (None, [], None, Some(day), None, None) => (1..=12).map(|m| format!("{}/{}", m, day)).filter(|r| parse_rule(r).is_ok()).collect(),
+        // "Mon 1 Sep" is the first Monday on or after Sep 1, "Mon 1 Jun --7" the last Monday of May
+        (None, [weekday], Some(month), Some(day @ (1 | 8 | 15 | 22)), None, None) => vec![format!("{}/{}#{}", month, weekday.num_days_from_sunday(), day.div_ceil(7))],
+        (None, [weekday], Some(month), Some(1), None, Some(7)) => vec![format!("{}/{}#5", if month == 1 { 12 } else { month - 1 }, weekday.num_days_from_sunday())],
+        _ => return Err("this combination of trigger fields has no ecal equivalent".to_string()),
+    };
+
+    let mut rules = Vec::new();
+    for rule_text in rule_texts {
+        let rule = parse_rule(&rule_text).map_err(|e| e.kind.to_string())?;
+        rules.push((rule_text, rule));
+    }
+    if rules.is_empty() { return Err("no valid date".to_string()); }
+    Ok(RemEntry { rules, category, description })
+}
+
+// The year of a "year(trigdate())-YYYY" expression and the body without the parenthesized count.
+// The expression is the [year(...)-YYYY] of {n} or the [ord(year(...)-YYYY)] of {nth}.
+fn counted_year(body: &str) -> Option<(i32, String)> {
+    let pos = body.find("year(trigdate())-")?;
+    let year = body.get(pos + 17..pos + 21)?.parse::<i32>().ok()?;
+    let open = body[..body[..pos].rfind('[')?].rfind(" (")?;
+    // The ')' closing the count, skipping parentheses inside [expressions] and the count text
+    let (mut parens, mut brackets) = (0, 0);
+    let (close, _) = body[open + 1..].char_indices().find(|(_, c)| {
+        match c {
+            '[' => brackets += 1,
+            ']' => brackets -= 1,
+            '(' if brackets == 0 => parens += 1,
+            ')' if brackets == 0 => parens -= 1,
+            _ => {}
+        }
+        parens == 0
+    })?;
+    Some((year, format!("{}{}", &body[..open], &body[open + 1 + close + 1..])))
+}
+
+// Splits on whitespace, keeping [bracketed expressions] together; tokens keep their byte offset
+fn tokenize(text: &str) -> Vec<(usize, String)> {
+    let mut tokens = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    let mut depth = 0;
+    for (pos, c) in text.char_indices() {
+        if c.is_whitespace() && depth <= 0 {
+            tokens.extend(current.take());
+            continue;
+        }
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            _ => {}
+        }
+        current.get_or_insert_with(|| (pos, String::new())).1.push(c);
+    }
+    tokens.extend(current);
+    tokens
+}
+
+// N of a [wkdaynum(trigdate())==N] condition
+fn satisfied_weekday(token: &str) -> Option<u32> {
+    let expr: String = token.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    let n = expr.strip_prefix("[wkdaynum(trigdate())==")?.strip_suffix(']')?;
+    n.parse().ok().filter(|n| *n <= 6)
+}
+
+// Offset from Easter Sunday of [easterdate(...)±N], [trigger(easterdate(...)±N)] or [var±N]
+fn easter_expression(token: &str, easter_vars: &[String]) -> Option<i64> {
+    let expr: String = token.to_lowercase().chars().filter(|c| !c.is_whitespace()).collect();
+    let mut expr = expr.trim_start_matches('[').trim_end_matches(']');
+    if let Some(inner) = expr.strip_prefix("trigger(").and_then(|e| e.strip_suffix(')')) {
+        expr = inner;
+    }
+    let rest = if let Some(args) = expr.strip_prefix("easterdate(") {
+        // Skip to the parenthesis closing easterdate(
+        let mut depth = 1;
+        let end = args.char_indices().find(|(_, c)| {
+            match c { '(' => depth += 1, ')' => depth -= 1, _ => {} }
+            depth == 0
+        })?.0;
+        &args[end + 1..]
+    } else {
+        let var = easter_vars.iter().find(|v| expr.starts_with(v.as_str()))?;
+        &expr[var.len()..]
+    };
+    match rest {
+        "" => Some(0),
+        _ if rest.starts_with(['+', '-']) => rest.parse::<i64>().ok().filter(|n| n.unsigned_abs() <= 366),
+        _ => None,
+    }
+}
+
+// The text between %" markers is what remind shows in calendars; other substitutions are dropped
+fn clean_body(body: &str) -> String {
+    let body = match body.split("%\"").nth(1) {
+        Some(quoted) if body.contains("%\"") => quoted,
+        _ => body,
+    };
+    let mut out = String::new();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' if chars.peek() == Some(&'%') => { chars.next(); out.push('%'); }
+            '%' => { chars.next(); }
+            c => out.push(c),
+        }
+    }
+    out.replace("[\"[\"]", "[").split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Writes every rule as a REM line; rules remind cannot express are kept as comments
+pub fn export_remind(rules: &RuleSet) -> String {
+    let today = chrono::Local::now().naive_local().date();
+    let mut out = format!("# Exported from ecal on {}\n", today.format("%Y-%m-%d"));
+
+    for (source, line_no, event_line) in &rules.event_lines {
+        let count_format = event_line.category.as_ref().and_then(|c| rules.categories.get(c)).and_then(|d| d.count_format.as_deref());
+        let mut message = escape_message(&event_line.description);
+        let trigger = match event_line.rule {
+            Rule::Fixed(date) => match count_format {
+                // Remind works out the number of years itself
+                Some(count_format) => {
+                    let years = format!("year(trigdate())-{}", date.year());
+                    let count = escape_message(count_format).replace("{nth}", &format!("[ord({})]", years)).replace("{n}", &format!("[{}]", years));
+                    message.push_str(&format!(" ({})", count));
+                    format!("{} {}", date.day(), short_month(date.month()))
+                }
+                None => format!("{} {} {}", date.day(), short_month(date.month()), date.year()),
+            },
+            // EASTERDATE of a date is the next Easter Sunday on or after it, so shifting the date by
+            // the offset first gives the next occurrence in whichever year that falls
+            Rule::Easter(0) => "[trigger(easterdate(today()))]".to_string(),
+            Rule::Easter(offset) => format!("[trigger(easterdate(today(){:+}){:+})]", -(offset as i128), offset),
+            // "Mon 1 Sep" triggers on the first Monday on or after Sep 1; the last one is a week
+            // back from the first of the next month
+            Rule::NthWeekday { month, weekday, n: 5 } => format!("{} {} 1 --7", short_weekday(weekday), short_month(month % 12 + 1)),
+            Rule::NthWeekday { month, weekday, n } => format!("{} {} {}", short_weekday(weekday), short_month(month), (n - 1) * 7 + 1),
+            Rule::Annual { month, day } => format!("{} {}", day, short_month(month)),
+            Rule::InYear { month, day, year } => format!("{} {} {}", day, short_month(month), year),
+            // "If MM/DD is a Saturday, two days later" is the later day when it falls on a Monday
+            Rule::IfWeekday { month, day, weekday, offset } if let Some((target_month, target_day)) = shifted_month_day(month, day, offset) => {
+                let target_weekday = (weekday.num_days_from_sunday() as i64 + offset.rem_euclid(7)) % 7;
+                format!("{} {} SATISFY [wkdaynum(trigdate())=={}]", target_day, short_month(target_month), target_weekday)
+            }
+            Rule::IfWeekday { .. } => {
+                out.push_str(&format!("# {}:{}: '{}' ({}) has no remind equivalent\n", source, line_no, event_line.rule_text, event_line.description));
+                continue;
+            }
+        };
+        let tag = event_line.category.as_ref().map(|c| format!(" TAG {}", c)).unwrap_or_default();
+        out.push_str(&format!("REM {}{} MSG {}\n", trigger, tag, message));
+    }
+    out
+}
+
+// Month and day `offset` days from MM/DD, when that is the same in leap and common years
+fn shifted_month_day(month: u32, day: u32, offset: i64) -> Option<(u32, u32)> {
+    let mut shifted = [1999, 2000, 2001].iter().map(|year| {
+        let date = NaiveDate::from_ymd_opt(*year, month, day)?.checked_add_signed(chrono::Duration::try_days(offset)?)?;
+        Some((date.month(), date.day()))
+    });
+    let first = shifted.next()??;
+    shifted.all(|s| s == Some(first)).then_some(first)
+}
+
+// Remind treats % and [ specially in message bodies
+fn escape_message(text: &str) -> String {
+    text.replace('%', "%%").replace('[', "[\"[\"]")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{CategoryDef, Line, parse_line};
+    use std::collections::HashMap;
+
+    fn rule_set(lines: &[&str]) -> RuleSet {
+        let event_lines = lines.iter().enumerate().filter_map(|(idx, text)| match parse_line(text) {
+            (Line::Event(event_line), _) => Some(("events.txt".to_string(), idx + 1, event_line)),
+            _ => None,
+        }).collect();
+        RuleSet { event_lines, categories: HashMap::new() }
+    }
+
+    #[test]
+    fn easter_is_computed_for_each_trigger() {
+        let exported = export_remind(&rule_set(&["E ;Easter Sunday", "E-2 ;Good Friday", "E+49 ;Whit Sunday"]));
+        assert!(!exported.to_lowercase().contains("year(today())"), "{}", exported);
+        assert!(exported.contains("REM [trigger(easterdate(today()))] MSG Easter Sunday\n"), "{}", exported);
+        assert!(exported.contains("REM [trigger(easterdate(today()+2)-2)] MSG Good Friday\n"), "{}", exported);
+        assert!(exported.contains("REM [trigger(easterdate(today()-49)+49)] MSG Whit Sunday\n"), "{}", exported);
+
+        let imported = import_remind(&exported);
+        assert!(imported.skipped.is_empty(), "{:?}", imported.skipped);
+        let rules: Vec<Rule> = imported.lines.iter().filter_map(|text| match parse_line(text) {
+            (Line::Event(event_line), _) => Some(event_line.rule),
+            _ => None,
+        }).collect();
+        assert_eq!(rules, vec![Rule::Easter(0), Rule::Easter(-2), Rule::Easter(49)]);
+    }
+
+    #[test]
+    fn counted_years_round_trip() {
+        let mut rules = rule_set(&["01-01-1950 ;[bday] Joe Sand", "02-06-2001 ;[wed] Ann & Bob", "03-03-1980 ;[club] Founded"]);
+        for (category, count_format) in [("bday", "{nth} Birthday"), ("wed", "{n} years married"), ("club", "turns {n} ({nth} year)")] {
+            rules.categories.insert(category.to_string(), CategoryDef { count_format: Some(count_format.to_string()), ..Default::default() });
+        }
+        let exported = export_remind(&rules);
+        assert!(exported.contains("REM 2 Jun TAG wed MSG Ann & Bob ([year(trigdate())-2001] years married)\n"), "{}", exported);
+
+        let imported = import_remind(&exported);
+        assert!(imported.skipped.is_empty(), "{:?}", imported.skipped);
+        assert_eq!(imported.lines, vec!["01-01-1950 ;[bday] Joe Sand", "02-06-2001 ;[wed] Ann & Bob", "03-03-1980 ;[club] Founded"]);
+    }
+
+    #[test]
+    fn weekday_conditions_round_trip() {
+        let exported = export_remind(&rule_set(&["3/17?6+2 ;St Patrick's Day (Monday)", "12/26?0+1 ;St Stephen's Day (Monday)"]));
+        assert!(exported.contains("REM 19 Mar SATISFY [wkdaynum(trigdate())==1] MSG"), "{}", exported);
+
+        let imported = import_remind(&exported);
+        assert!(imported.skipped.is_empty(), "{:?}", imported.skipped);
+        let rules: Vec<Rule> = imported.lines.iter().filter_map(|text| match parse_line(text) {
+            (Line::Event(event_line), _) => Some(event_line.rule),
+            _ => None,
+        }).collect();
+        let originals = rule_set(&["3/17?6+2 ;a", "12/26?0+1 ;b"]);
+        for (imported, (_, _, original)) in rules.iter().zip(&originals.event_lines) {
+            for year in 2020..2040 {
+                assert_eq!(imported.date_in_year(year), original.rule.date_in_year(year), "{:?} in {}", original.rule_text, year);
+            }
+        }
+        assert_eq!(rules.len(), 2);
+        assert!(!import_remind("REM 1 Jan SATISFY [isomitted(trigdate())] MSG x").skipped.is_empty());
+    }
+
+    #[test]
+    fn counted_years_keep_only_a_tagged_category() {
+        let imported = import_remind("REM 2 Jun MSG Ann & Bob ([year(trigdate())-2001] years married)\n\
+                                      REM 1 Jan TAG anni MSG First kiss ([ord(year(trigdate())-2011)] Anniversary)\n");
+        assert_eq!(imported.lines, vec!["6/2       ; Ann & Bob", "01-01-2011 ;[anni] First kiss"]);
+    }
+}
@@ -0,0 +1,47 @@
+// Pieces shared by the renderers: terminal and plain text, markdown, HTML, SVG and LaTeX.
+
+use crate::{Config, Event, format_anniversary_count};
+use chrono::NaiveDate;
+
+const WEEKDAY_ABBREVIATIONS: [&str; 7] = ["Su", "Mo", "Tu", "We", "Th", "Fr", "Sa"];
+const WEEKDAY_NAMES: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+
+// Two-letter weekday names in the order of the calendar columns
+pub fn weekday_abbreviations(monday_first: bool) -> [&'static str; 7] {
+    in_column_order(WEEKDAY_ABBREVIATIONS, monday_first)
+}
+
+pub fn weekday_names(monday_first: bool) -> [&'static str; 7] {
+    in_column_order(WEEKDAY_NAMES, monday_first)
+}
+
+fn in_column_order(mut names: [&'static str; 7], monday_first: bool) -> [&'static str; 7] {
+    if monday_first { names.rotate_left(1); }
+    names
+}
+
+// Months side by side in the calendar; a single month is never spread over columns
+pub fn months_per_row(config: &Config) -> usize {
+    if config.num_months == 1 { 1 } else { config.num_columns.max(1) }
+}
+
+// The description followed by the year count of birthdays and anniversaries
+pub fn full_description(event: &Event) -> String {
+    match format_anniversary_count(event) {
+        Some(count_text) => format!("{} ({})", event.description, count_text),
+        None => event.description.clone(),
+    }
+}
+
+// " (Today 📌)", " (In 3 days)" or " (3 days ago)", highlighted for the terminal when `ansi` is set
+pub fn relative_days_label(date: NaiveDate, today: NaiveDate, ansi: bool) -> String {
+    let days_diff = date.signed_duration_since(today).num_days();
+    match (days_diff, ansi) {
+        (0, true) => " \x1b[1m\x1b[33m(Today 📌)\x1b[0m".to_string(),
+        (0, false) => " (Today 📌)".to_string(),
+        (1.., true) => format!(" \x1b[32m(In \x1b[1m{}\x1b[0m\x1b[32m days)\x1b[0m", days_diff),
+        (1.., false) => format!(" (In {} days)", days_diff),
+        (_, true) => format!(" \x1b[34m(\x1b[1m{}\x1b[0m\x1b[34m days ago)\x1b[0m", days_diff.abs()),
+        (_, false) => format!(" ({} days ago)", days_diff.abs()),
+    }
+}
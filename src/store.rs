@@ -0,0 +1,89 @@
+// Writing the events file. A lock file keeps concurrent ecal runs from interleaving their
+// changes, the previous content is kept as a backup, and the new content replaces the file with a
+// rename so it is never half written.
+
+use crate::history;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const LOCK_ATTEMPTS: u32 = 50;
+const LOCK_RETRY: Duration = Duration::from_millis(100);
+
+// Removes the lock file when dropped
+pub struct FileLock {
+    path: PathBuf,
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+pub fn lock(path: &str) -> Result<FileLock, String> {
+    let lock_path = PathBuf::from(format!("{}.lock", path));
+    for _ in 0..LOCK_ATTEMPTS {
+        match fs::OpenOptions::new().write(true).create_new(true).open(&lock_path) {
+            Ok(mut file) => {
+                let _ = writeln!(file, "{}", std::process::id());
+                return Ok(FileLock { path: lock_path });
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => std::thread::sleep(LOCK_RETRY),
+            Err(e) => return Err(format!("Cannot create lock file '{}': {}", lock_path.display(), e)),
+        }
+    }
+    Err(format!("'{}' is locked by another ecal process. Remove '{}' if none is running.", path, lock_path.display()))
+}
+
+// Applies `change` to the content of the file (empty if it doesn't exist yet) while holding the lock
+pub fn modify<F>(path: &str, change: F) -> Result<(), String>
+where
+    F: FnOnce(&str) -> Result<String, String>,
+{
+    let _lock = lock(path)?;
+    let content = match fs::read(path) {
+        Ok(bytes) => String::from_utf8(bytes).map_err(|_| format!("'{}' is not valid UTF-8; run 'ecal check' and fix it first.", path))?,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Cannot read '{}': {}", path, e)),
+    };
+    let new_content = change(&content)?;
+    if new_content == content {
+        return Ok(());
+    }
+    write(path, &content, &new_content)
+}
+
+// The file a path refers to; a symlinked events file keeps its link and shares its backups
+pub fn resolve(path: &str) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path))
+}
+
+// Backs up the old content, writes next to the file and renames over it
+fn write(path: &str, old_content: &str, content: &str) -> Result<(), String> {
+    let target = resolve(path);
+    if target.exists() {
+        history::backup(&target, old_content)?;
+    }
+    let temp = PathBuf::from(format!("{}.tmp", target.display()));
+    fs::write(&temp, content).map_err(|e| format!("Cannot write '{}': {}", temp.display(), e))?;
+    if let Ok(metadata) = fs::metadata(&target) {
+        let _ = fs::set_permissions(&temp, metadata.permissions());
+    }
+    fs::rename(&temp, &target).map_err(|e| format!("Cannot replace '{}': {}", target.display(), e))
+}
+
+// Appends lines to the content, separated from what is there by a blank line
+pub fn append_lines(content: &str, lines: &[String]) -> String {
+    let mut out = content.to_string();
+    if !out.is_empty() {
+        if !out.ends_with('\n') { out.push('\n'); }
+        out.push('\n');
+    }
+    for line in lines {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
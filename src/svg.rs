@@ -0,0 +1,173 @@
+// SVG year planner: twelve month grids laid out on a printable page, with a colored marker on
+// every day that has events and a legend of the categories shown.
+
+use crate::html::escape;
+use crate::render::weekday_abbreviations;
+use crate::{Config, Event, css_color, displayed_months, events_on, month_name, month_weeks};
+use chrono::{Datelike, NaiveDate, Weekday};
+
+const WEEKEND_COLOR: &str = "#cd3131";
+const WEEK_NUMBER_COLOR: &str = "#2472c8";
+const DEFAULT_MARKER_COLOR: &str = "#555555";
+
+// Page size in millimetres
+#[derive(Debug, Clone, Copy)]
+pub struct PaperSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Default for PaperSize {
+    fn default() -> Self {
+        PaperSize { width: 297.0, height: 210.0 } // A4 landscape
+    }
+}
+
+// Accepts a paper name (landscape; add "-portrait" to turn it) or WIDTHxHEIGHT in millimetres
+pub fn parse_paper_size(text: &str) -> Option<PaperSize> {
+    let text = text.trim().to_lowercase();
+    let (name, portrait) = match text.strip_suffix("-portrait") {
+        Some(name) => (name, true),
+        None => (text.strip_suffix("-landscape").unwrap_or(&text), false),
+    };
+    let (long, short) = match name {
+        "a2" => (594.0, 420.0),
+        "a3" => (420.0, 297.0),
+        "a4" => (297.0, 210.0),
+        "a5" => (210.0, 148.0),
+        "letter" => (279.4, 215.9),
+        "legal" => (355.6, 215.9),
+        "tabloid" => (431.8, 279.4),
+        _ => {
+            let (width, height) = name.split_once('x')?;
+            let size = PaperSize { width: width.trim().parse().ok()?, height: height.trim().parse().ok()? };
+            let valid = |v: f64| v.is_finite() && (50.0..=5000.0).contains(&v);
+            return (valid(size.width) && valid(size.height)).then_some(size);
+        }
+    };
+    Some(if portrait { PaperSize { width: short, height: long } } else { PaperSize { width: long, height: short } })
+}
+
+pub fn render_svg(config: &Config, events: &[Event], paper: PaperSize) -> String {
+    let today = chrono::Local::now().naive_local().date();
+    let months = displayed_months(config);
+    let (cols, rows) = if paper.width >= paper.height { (4, 3) } else { (3, 4) };
+
+    let margin = paper.width.min(paper.height) * 0.05;
+    let title_height = paper.height * 0.07;
+    let legend_height = paper.height * 0.06;
+    let gap = margin * 0.6;
+    let block_width = (paper.width - 2.0 * margin - (cols - 1) as f64 * gap) / cols as f64;
+    let block_height = (paper.height - 2.0 * margin - title_height - legend_height - (rows - 1) as f64 * gap) / rows as f64;
+
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}mm\" height=\"{h}mm\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\">\n",
+        w = paper.width, h = paper.height
+    );
+    out.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>\n", paper.width, paper.height));
+
+    let title = match (months.first(), months.last()) {
+        (Some(first), Some(last)) if first.month() == 1 && last.year() == first.year() => first.year().to_string(),
+        (Some(first), Some(last)) => format!("{} {} – {} {}", month_name(first.month()), first.year(), month_name(last.month()), last.year()),
+        _ => String::new(),
+    };
+    out.push_str(&format!(
+        "<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.2}\" font-weight=\"bold\" text-anchor=\"middle\">{}</text>\n",
+        paper.width / 2.0, margin + title_height * 0.6, title_height * 0.6, escape(&title)
+    ));
+
+    for (idx, month_start) in months.iter().enumerate() {
+        let x = margin + (idx % cols) as f64 * (block_width + gap);
+        let y = margin + title_height + (idx / cols) as f64 * (block_height + gap);
+        render_month(&mut out, *month_start, (x, y, block_width, block_height), config, events, today);
+    }
+
+    render_legend(&mut out, &months, events, (margin, paper.height - margin - legend_height * 0.4, legend_height * 0.4));
+    out.push_str("</svg>\n");
+    out
+}
+
+// Same grid as format_month_row: caption, weekday header, then one row per week
+fn render_month(out: &mut String, month_start: NaiveDate, (x, y, width, height): (f64, f64, f64, f64), config: &Config, events: &[Event], today: NaiveDate) {
+    let columns = if config.show_week_numbers { 8 } else { 7 };
+    let cell_width = width / columns as f64;
+    let cell_height = height / 8.0; // Caption, header and up to six weeks
+    let font_size = (cell_width * 0.45).min(cell_height * 0.6);
+    let first_day_column = columns - 7;
+
+    out.push_str(&format!(
+        "<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.2}\" font-weight=\"bold\" text-anchor=\"middle\">{} {}</text>\n",
+        x + width / 2.0, y + cell_height * 0.7, font_size * 1.2, month_name(month_start.month()), month_start.year()
+    ));
+
+    let text = |out: &mut String, column: usize, row: usize, fill: &str, weight: &str, content: &str| {
+        out.push_str(&format!(
+            "<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.2}\" text-anchor=\"middle\" fill=\"{}\"{}>{}</text>\n",
+            x + (column as f64 + 0.5) * cell_width, y + (row as f64 + 0.7) * cell_height, font_size, fill, weight, content
+        ));
+    };
+
+    if config.show_week_numbers { text(out, 0, 1, WEEK_NUMBER_COLOR, "", "Wk"); }
+    for (column, name) in weekday_abbreviations(config.monday_first).iter().enumerate() {
+        let fill = if *name == "Sa" || *name == "Su" { WEEKEND_COLOR } else { "#000000" };
+        text(out, first_day_column + column, 1, fill, " font-weight=\"bold\"", name);
+    }
+
+    for (week, (week_number, days)) in month_weeks(month_start, config.monday_first).into_iter().enumerate() {
+        let row = week + 2;
+        if config.show_week_numbers { text(out, 0, row, WEEK_NUMBER_COLOR, "", &week_number.to_string()); }
+        for (column, date) in days.iter().enumerate() {
+            let Some(date) = *date else { continue };
+            let column = first_day_column + column;
+            let day_events = events_on(events, date);
+            let cell_x = x + column as f64 * cell_width;
+            let cell_y = y + row as f64 * cell_height;
+
+            if let Some(event) = day_events.first() {
+                let descriptions: Vec<String> = day_events.iter().map(|e| escape(&e.description)).collect();
+                out.push_str(&format!(
+                    "<g><title>{}</title><circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\" fill-opacity=\"0.35\"/></g>\n",
+                    descriptions.join("\n"), cell_x + cell_width / 2.0, cell_y + cell_height / 2.0, cell_width.min(cell_height) * 0.45, marker_color(event)
+                ));
+            }
+            if date == today {
+                out.push_str(&format!(
+                    "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"none\" stroke=\"#000000\" stroke-width=\"{:.2}\"/>\n",
+                    cell_x + cell_width * 0.05, cell_y + cell_height * 0.05, cell_width * 0.9, cell_height * 0.9, font_size * 0.08
+                ));
+            }
+            let is_weekend = matches!(date.weekday(), Weekday::Sat | Weekday::Sun);
+            let fill = if is_weekend { WEEKEND_COLOR } else { "#000000" };
+            let weight = if day_events.is_empty() { "" } else { " font-weight=\"bold\"" };
+            text(out, column, row, fill, weight, &date.day().to_string());
+        }
+    }
+}
+
+// One entry per category of the events shown, in order of first appearance
+fn render_legend(out: &mut String, months: &[NaiveDate], events: &[Event], (x, y, size): (f64, f64, f64)) {
+    let (Some(first), Some(last)) = (months.first(), months.last()) else { return };
+    let end = last.checked_add_months(chrono::Months::new(1)).unwrap_or(*last);
+    let mut entries: Vec<(String, &str)> = Vec::new();
+    for event in events.iter().filter(|e| e.date >= *first && e.date < end) {
+        let name = event.label.clone().or_else(|| event.category.clone()).unwrap_or_else(|| "Other".to_string());
+        if !entries.iter().any(|(n, _)| *n == name) {
+            entries.push((name, marker_color(event)));
+        }
+    }
+
+    let mut entry_x = x;
+    for (name, color) in entries {
+        out.push_str(&format!(
+            "<circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"{:.2}\" fill=\"{}\" fill-opacity=\"0.35\"/>\n<text x=\"{:.2}\" y=\"{:.2}\" font-size=\"{:.2}\">{}</text>\n",
+            entry_x + size / 2.0, y + size / 2.0, size / 2.0, color, entry_x + size * 1.3, y + size * 0.85, size, escape(&name)
+        ));
+        entry_x += size * (2.5 + name.chars().count() as f64 * 0.6);
+    }
+}
+
+fn marker_color(event: &Event) -> &'static str {
+    event.bg_color.as_deref().and_then(css_color)
+        .or_else(|| event.fg_color.as_deref().and_then(css_color))
+        .unwrap_or(DEFAULT_MARKER_COLOR)
+}
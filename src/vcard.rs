@@ -0,0 +1,181 @@
+// Import of birthdays and anniversaries from vCard (.vcf) contact exports. Full dates become
+// DD-MM-YYYY lines so the years get counted, dates without a year become yearly MM/DD rules.
+
+use crate::ImportReport;
+use crate::parser::{EventLine, Line, Rule, parse_source};
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashSet;
+
+// A date from BDAY or ANNIVERSARY; vCards allow leaving out the year
+#[derive(Debug, Clone, Copy)]
+enum ContactDate {
+    Full(NaiveDate),
+    YearLess { month: u32, day: u32 },
+}
+
+pub fn import_vcard(content: &str, events_file: &str, existing: &str) -> ImportReport {
+    let mut report = ImportReport::default();
+    let existing = existing_dates(events_file, existing);
+    let mut imported = HashSet::new();
+
+    for card in parse_cards(content) {
+        let Some(name) = card.name.clone() else {
+            if card.birthday.is_some() || card.anniversary.is_some() { report.skipped.push("a contact without a name".to_string()); }
+            continue;
+        };
+        for (category, value) in [("bday", &card.birthday), ("anni", &card.anniversary)] {
+            let Some(value) = value else { continue };
+            let Some(date) = parse_contact_date(value) else {
+                report.skipped.push(format!("{} ('{}' is not a date)", name, value));
+                continue;
+            };
+            let (month, day) = match date {
+                ContactDate::Full(date) => (date.month(), date.day()),
+                ContactDate::YearLess { month, day } => (month, day),
+            };
+            let key = (category.to_string(), month, day, normalized(&name));
+            if existing.iter().any(|(c, m, d, description)| c == category && (*m, *d) == (month, day) && contains_words(description, &key.3)) {
+                report.skipped.push(format!("{} ({} already in the events file)", name, category));
+                continue;
+            }
+            if !imported.insert(key) {
+                report.skipped.push(format!("{} (duplicate {} in the contacts)", name, category));
+                continue;
+            }
+            let (rule, rule_text) = match date {
+                ContactDate::Full(date) => (Rule::Fixed(date), date.format("%d-%m-%Y").to_string()),
+                ContactDate::YearLess { month, day } => (Rule::Annual { month, day }, format!("{}/{}", month, day)),
+            };
+            let line = EventLine { rule, rule_text, category: Some(category.to_string()), fg_color: None, bg_color: None, description: name.clone() };
+            report.lines.push(line.to_line());
+            report.recurring += 1;
+        }
+    }
+    report
+}
+
+// (category, month, day, normalized description) of the birthdays and anniversaries already in the file
+fn existing_dates(events_file: &str, existing: &str) -> Vec<(String, u32, u32, String)> {
+    let (lines, _) = parse_source(events_file, existing);
+    lines.into_iter().filter_map(|(_, line)| {
+        let Line::Event(event_line) = line else { return None };
+        let category = event_line.category.filter(|c| c == "bday" || c == "anni")?;
+        let (month, day) = match event_line.rule {
+            Rule::Fixed(date) => (date.month(), date.day()),
+            Rule::Annual { month, day } => (month, day),
+            _ => return None,
+        };
+        Some((category, month, day, normalized(&event_line.description)))
+    }).collect()
+}
+
+// Lowercase words without emoji or punctuation, so "🎂 Joe Sand!" matches the contact "Joe Sand"
+fn normalized(text: &str) -> String {
+    let text: String = text.chars().map(|c| if c.is_alphanumeric() { c } else { ' ' }).collect();
+    text.to_lowercase().split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Whether the words of `name` appear together in `description`, both normalized
+fn contains_words(description: &str, name: &str) -> bool {
+    !name.is_empty() && format!(" {} ", description).contains(&format!(" {} ", name))
+}
+
+#[derive(Default)]
+struct Card {
+    name: Option<String>,
+    birthday: Option<String>,
+    anniversary: Option<String>,
+}
+
+fn parse_cards(content: &str) -> Vec<Card> {
+    // Unfold continuation lines (starting with a space or tab)
+    let mut lines: Vec<String> = Vec::new();
+    for line in content.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.trim_end_matches('\r').to_string()),
+        }
+    }
+
+    let mut cards = Vec::new();
+    let mut current: Option<Card> = None;
+    for line in &lines {
+        let Some((name_part, value)) = line.split_once(':') else { continue };
+        let mut params = name_part.split(';');
+        // Drop the group prefix of grouped properties like "item1.X-ABDATE"
+        let property = params.next().unwrap_or("").rsplit('.').next().unwrap_or("").to_uppercase();
+        let params: Vec<String> = params.map(str::to_uppercase).collect();
+
+        match (property.as_str(), value.trim().to_uppercase().as_str()) {
+            ("BEGIN", "VCARD") => current = Some(Card::default()),
+            ("END", "VCARD") => cards.extend(current.take()),
+            _ => {}
+        }
+        let Some(card) = current.as_mut() else { continue };
+        let value = value.trim();
+        match property.as_str() {
+            "FN" if !value.is_empty() => card.name = Some(unescape(value)),
+            "N" if card.name.is_none() => {
+                let parts: Vec<String> = value.split(';').map(unescape).collect();
+                let name = [parts.get(3), parts.get(1), parts.get(2), parts.first(), parts.get(4)].into_iter().flatten()
+                    .filter(|p| !p.is_empty()).cloned().collect::<Vec<_>>().join(" ");
+                if !name.is_empty() { card.name = Some(name); }
+            }
+            // Apple marks dates without a year by storing them in the year 1604
+            "BDAY" if params.iter().any(|p| p.starts_with("X-APPLE-OMIT-YEAR")) => card.birthday = Some(format!("--{}", value.get(5..).unwrap_or(value))),
+            "BDAY" => card.birthday = Some(value.to_string()),
+            "ANNIVERSARY" | "X-ANNIVERSARY" | "X-MS-ANNIVERSARY" | "X-EVOLUTION-ANNIVERSARY" => card.anniversary = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    cards
+}
+
+// 1990-03-05, 19900305, --03-05, --0305, optionally followed by a time
+fn parse_contact_date(value: &str) -> Option<ContactDate> {
+    let date = value.split('T').next()?.trim();
+    if let Some(rest) = date.strip_prefix("--") {
+        let digits: String = rest.chars().filter(|c| *c != '-').collect();
+        if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_digit()) { return None; }
+        let (month, day) = (digits[..2].parse().ok()?, digits[2..].parse().ok()?);
+        // 2000 is a leap year, so Feb 29 is accepted
+        NaiveDate::from_ymd_opt(2000, month, day)?;
+        return Some(ContactDate::YearLess { month, day });
+    }
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").or_else(|_| NaiveDate::parse_from_str(date, "%Y%m%d")).ok().map(ContactDate::Full)
+}
+
+fn unescape(value: &str) -> String {
+    value.replace("\\,", ",").replace("\\;", ";").replace("\\n", " ").replace("\\N", " ").replace("\\\\", "\\").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTACTS: &str = "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Joe Sand\r\nBDAY:1950-01-01\r\nEND:VCARD\r\n\
+                            BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Ann O'Neill\r\nBDAY:--07-14\r\nANNIVERSARY:2001-06-02\r\nEND:VCARD\r\n";
+
+    #[test]
+    fn contacts_already_in_the_file_are_skipped() {
+        let existing = "01-01-1950 ;[bday, magenta] 🎂 Joe Sand\n7/14 ;[bday] Ann O’Neill's birthday\n";
+        let report = import_vcard(CONTACTS, "events.txt", existing);
+        assert_eq!(report.lines, vec!["02-06-2001 ;[anni] Ann O'Neill".to_string()]);
+        assert_eq!(report.skipped.len(), 2);
+    }
+
+    #[test]
+    fn other_categories_dates_and_names_are_not_duplicates() {
+        let existing = "01-01-1950 ;[anni] Joe Sand\n02-01-1950 ;[bday] Joe Sand\n01-01-1950 ;[bday] Joe Sanders\n";
+        let report = import_vcard(CONTACTS, "events.txt", existing);
+        assert!(report.lines.contains(&"01-01-1950 ;[bday] Joe Sand".to_string()), "{:?}", report.lines);
+    }
+
+    #[test]
+    fn importing_twice_adds_nothing() {
+        let first = import_vcard(CONTACTS, "events.txt", "");
+        assert_eq!(first.lines.len(), 3);
+        let second = import_vcard(CONTACTS, "events.txt", &first.lines.join("\n"));
+        assert!(second.lines.is_empty(), "{:?}", second.lines);
+    }
+}